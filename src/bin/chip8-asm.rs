@@ -0,0 +1,72 @@
+use std::{fs, path::Path, process};
+
+use chip8_rust::emulation::{asm, octo, MemoryLayout, Platform};
+
+fn main() {
+    // Usage: chip8-asm <source> [-o <rom>] [--layout default|eti660]
+    // .8o sources are Octo programs, anything else uses the chip8-disasm mnemonics.
+    // Octo programs always start at 0x200, the layout only moves the others.
+    let mut source_path = None;
+    let mut rom_path = None;
+    let mut layout = MemoryLayout::for_platform(Platform::XoChip);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            rom_path = args.next();
+        } else if arg == "--layout" {
+            let name = args.next().unwrap_or_default();
+            match MemoryLayout::from_name(&name, Platform::XoChip) {
+                Some(selected) => layout = selected,
+                None => {
+                    eprintln!("Unknown memory layout: {}", name);
+                    process::exit(1);
+                }
+            }
+        } else {
+            source_path = Some(arg);
+        }
+    }
+
+    let source_path = match source_path {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: chip8-asm <source> [-o <rom>] [--layout default|eti660]");
+            process::exit(1);
+        }
+    };
+    // Default to the source file name with a .ch8 extension
+    let rom_path = rom_path.unwrap_or_else(|| {
+        Path::new(&source_path)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    let source = match fs::read_to_string(&source_path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", source_path, err);
+            process::exit(1);
+        }
+    };
+    let is_octo = Path::new(&source_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("8o"));
+    let assembled = if is_octo {
+        octo::compile(&source)
+    } else {
+        asm::assemble_with_layout(&source, layout)
+    };
+    let rom = match assembled {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("{}:{}", source_path, err);
+            process::exit(1);
+        }
+    };
+    if let Err(err) = fs::write(&rom_path, &rom) {
+        eprintln!("{}: {}", rom_path, err);
+        process::exit(1);
+    }
+    println!("Wrote {} bytes to {}", rom.len(), rom_path);
+}
@@ -0,0 +1,57 @@
+use std::process;
+
+use chip8_rust::emulation::{disasm, MemoryLayout};
+use chip8_rust::{Disk, Platform};
+
+fn main() {
+    // Usage: chip8-disasm <rom> [--platform chip8|schip|xochip] [--layout default|eti660]
+    let mut rom_path = None;
+    let mut platform = Platform::Chip8;
+    let mut layout_name = String::from("default");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--platform" {
+            let name = args.next().unwrap_or_default();
+            match Platform::from_name(&name) {
+                Some(selected) => platform = selected,
+                None => {
+                    eprintln!("Unknown platform: {}", name);
+                    process::exit(1);
+                }
+            }
+        } else if arg == "--layout" {
+            layout_name = args.next().unwrap_or_default();
+        } else {
+            rom_path = Some(arg);
+        }
+    }
+
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: chip8-disasm <rom> [--platform chip8|schip|xochip] [--layout default|eti660]");
+            process::exit(1);
+        }
+    };
+
+    let layout = match MemoryLayout::from_name(&layout_name, platform) {
+        Some(layout) => layout,
+        None => {
+            eprintln!("Unknown memory layout: {}", layout_name);
+            process::exit(1);
+        }
+    };
+
+    let disk = match Disk::load_with_layout(&rom_path, layout) {
+        Ok(disk) => disk,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+    println!("; {} ({} bytes)", rom_path, disk.size);
+    print!(
+        "{}",
+        disasm::disassemble_with_layout(&disk.rom, platform, layout)
+    );
+}
@@ -0,0 +1,282 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    process,
+    time::{Duration, Instant},
+};
+
+use chip8_rust::emulation::{self, Cartridge, KeyTimeouts, TextRendering, FRAME_RATE};
+use chip8_rust::{Cpu, CpuError, Disk, Platform, Quirks, Scheduler};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+const USAGE: &str = "Usage: chip8-tui <rom> [--platform chip8|schip|xochip] \
+                     [--quirks vip|chip48|schip|modern] [--ipf <n>] [--seed <n>] \
+                     [--render half-block|braille] [--hold <frames>]";
+
+// Frames a keypad key stays down after a press when the terminal doesn't report
+// releases, a held key repeats before it comes up for long
+const DEFAULT_HOLD_FRAMES: u32 = 10;
+
+// Raw mode on the alternate screen, restored when dropped, also when the emulator panics
+struct Terminal {
+    // The terminal reports key releases
+    releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(stdout, PushKeyboardEnhancementFlags(flags))?;
+        }
+        Ok(Terminal { releases })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() {
+    let mut rom_path = None;
+    let mut platform = None;
+    let mut quirks = None;
+    let mut instructions_per_frame = None;
+    let mut seed = None;
+    let mut rendering = None;
+    let mut hold_frames = DEFAULT_HOLD_FRAMES;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = if arg == "--platform" {
+            let name = args.next().unwrap_or_default();
+            Platform::from_name(&name).map(|selected| platform = Some(selected))
+        } else if arg == "--quirks" {
+            let name = args.next().unwrap_or_default();
+            Quirks::from_name(&name).map(|preset| quirks = Some(preset))
+        } else if arg == "--ipf" {
+            let value = args.next().unwrap_or_default();
+            value
+                .parse()
+                .ok()
+                .map(|ipf| instructions_per_frame = Some(ipf))
+        } else if arg == "--seed" {
+            let value = args.next().unwrap_or_default();
+            value.parse().ok().map(|number| seed = Some(number))
+        } else if arg == "--render" {
+            let name = args.next().unwrap_or_default();
+            TextRendering::from_name(&name).map(|selected| rendering = Some(selected))
+        } else if arg == "--hold" {
+            let value = args.next().unwrap_or_default();
+            value.parse().ok().map(|frames| hold_frames = frames)
+        } else if arg.starts_with("--") {
+            None
+        } else {
+            rom_path = Some(arg.clone());
+            Some(())
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument: {}\n{}", arg, USAGE);
+            process::exit(1);
+        }
+    }
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    // Octo cartridges are compiled and bring their settings along
+    let is_cartridge = Path::new(&rom_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    let loaded = if is_cartridge {
+        Cartridge::load(&rom_path)
+            .map(|cartridge| (Disk::from_rom(cartridge.rom.clone()), Some(cartridge)))
+            .map_err(|err| err.to_string())
+    } else {
+        Disk::load(&rom_path, platform.unwrap_or(Platform::XoChip))
+            .map(|disk| (disk, None))
+            .map_err(|err| err.to_string())
+    };
+    let (disk, cartridge) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    // Command line settings win over the cartridge, the ROM database and the file extension
+    let info = disk.info;
+    let extension_platform = Path::new(&rom_path)
+        .extension()
+        .and_then(|extension| Platform::from_extension(&extension.to_string_lossy()));
+    let platform = platform
+        .or(cartridge.as_ref().map(|cartridge| cartridge.platform))
+        .or(info.map(|info| info.platform))
+        .or(extension_platform)
+        .unwrap_or(Platform::Chip8);
+    let quirks = quirks
+        .or(cartridge.as_ref().map(|cartridge| cartridge.quirks))
+        .or(info.map(|info| info.quirks))
+        .unwrap_or_else(|| platform.default_quirks());
+    let instructions_per_frame = instructions_per_frame
+        .or(cartridge
+            .as_ref()
+            .map(|cartridge| cartridge.instructions_per_frame))
+        .or(info.map(|info| info.instructions_per_frame))
+        .unwrap_or(emulation::DEFAULT_INSTRUCTIONS_PER_FRAME);
+    let title = info.map_or(rom_path.as_str(), |info| info.title);
+
+    let mut cpu = Cpu::with_platform(platform, quirks);
+    if let Some(seed) = seed {
+        cpu.seed_rng(seed);
+    }
+    if let Err(err) = cpu.load_disk_to_ram(&disk) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+    let mut scheduler = Scheduler::new(instructions_per_frame);
+
+    let result = Terminal::enter().and_then(|terminal| {
+        run(
+            &mut cpu,
+            &mut scheduler,
+            &terminal,
+            title,
+            rendering,
+            hold_frames,
+        )
+    });
+    if let Err(err) = result {
+        eprintln!("Terminal error: {}", err);
+        process::exit(1);
+    }
+}
+
+// Run the program until Esc or Ctrl+C, drawing whenever the framebuffer changed
+fn run(
+    cpu: &mut Cpu,
+    scheduler: &mut Scheduler,
+    terminal: &Terminal,
+    title: &str,
+    rendering: Option<TextRendering>,
+    hold_frames: u32,
+) -> io::Result<()> {
+    let frame = Duration::from_secs(1) / FRAME_RATE;
+    let mut keys = KeyTimeouts::new(hold_frames);
+    let mut halted: Option<CpuError> = None;
+    let mut layout = None;
+    let mut last = Instant::now();
+
+    loop {
+        // Wait for input until the next frame is due
+        let mut wait = frame;
+        let mut redraw = false;
+        while event::poll(wait)? {
+            wait = Duration::ZERO;
+            match event::read()? {
+                Event::Key(key) => {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.code == KeyCode::Esc || ctrl_c {
+                        return Ok(());
+                    }
+                    let keypad = match key.code {
+                        KeyCode::Char(c) => emulation::keypad_key(c),
+                        _ => None,
+                    };
+                    if let Some(keypad) = keypad {
+                        match key.kind {
+                            KeyEventKind::Release => keys.release(cpu, keypad),
+                            // Without release events a key is let go after the hold time
+                            _ if !terminal.releases => keys.press(cpu, keypad),
+                            _ => cpu.key_pressed(keypad),
+                        }
+                    }
+                }
+                Event::Resize(..) => redraw = true,
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        if halted.is_none() {
+            match scheduler.advance(cpu, now - last) {
+                Ok(frames) => (0..frames).for_each(|_| keys.tick(cpu)),
+                Err(err) => {
+                    halted = Some(err);
+                    redraw = true;
+                }
+            }
+        }
+        last = now;
+
+        if cpu.framebuffer.take_dirty() || redraw {
+            draw(cpu, title, rendering, halted, &mut layout)?;
+        }
+    }
+}
+
+// Print the framebuffer from the top left corner, half blocks unless the terminal is too
+// narrow for them. The screen is cleared when the picture changes its size.
+fn draw(
+    cpu: &Cpu,
+    title: &str,
+    rendering: Option<TextRendering>,
+    halted: Option<CpuError>,
+    layout: &mut Option<(TextRendering, usize, (u16, u16))>,
+) -> io::Result<()> {
+    let size = terminal::size()?;
+    let framebuffer = &cpu.framebuffer;
+    let rendering = rendering.unwrap_or(if (size.0 as usize) < framebuffer.width() {
+        TextRendering::Braille
+    } else {
+        TextRendering::HalfBlock
+    });
+
+    let mut stdout = io::stdout();
+    let current = Some((rendering, framebuffer.width(), size));
+    if *layout != current {
+        *layout = current;
+        queue!(stdout, Clear(ClearType::All))?;
+    }
+
+    let lines = rendering.render(framebuffer);
+    for (row, line) in lines.iter().enumerate() {
+        queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+    }
+    let status = match halted {
+        Some(err) => format!("{} halted: {}", title, err),
+        None => format!("{} - Esc quits", title),
+    };
+    queue!(
+        stdout,
+        MoveTo(0, lines.len() as u16),
+        Clear(ClearType::CurrentLine),
+        Print(status)
+    )?;
+    stdout.flush()
+}
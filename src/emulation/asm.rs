@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Instruction, MemoryLayout, Platform};
+
+#[cfg(test)]
+#[path = "./tests/asm.rs"]
+mod tests;
+
+// Mnemonics known to the assembler, for telling unknown ones from misused ones
+const MNEMONICS: [&str; 32] = [
+    "SYS", "CLS", "RET", "JP", "CALL", "SE", "SNE", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR",
+    "SUBN", "SHL", "RND", "DRW", "SKP", "SKNP", "SCD", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SCU",
+    "SAVE", "LOAD", "PLANE", "AUDIO", "PITCH",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// The line is filled in once the error leaves the line it occurred in
+fn error(column: usize, message: impl Into<String>) -> AsmError {
+    AsmError {
+        line: 0,
+        column,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Text(String),
+    Comma,
+    Colon,
+    Equals,
+    Plus,
+    Minus,
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+    token: Token,
+    column: usize,
+}
+
+// Tokens between two commas
+#[derive(Debug)]
+struct Operand {
+    column: usize,
+    lexemes: Vec<Lexeme>,
+}
+
+// A line that emits bytes
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    address: usize,
+    mnemonic: String,
+    column: usize,
+    operands: Vec<Operand>,
+}
+
+// Operand after resolving registers, keywords and expressions
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arg {
+    V(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Hf,
+    R,
+    Long(i64),
+    Value(i64),
+}
+
+// Assemble source into a ROM that is loaded at 0x200, into the 64 KiB of XO-CHIP.
+//
+// One statement per line, comments start with ';':
+//   name = 0x10            constant
+//   loop: ADD V0, name     label, optionally followed by a statement
+//   db 0x01, 2, -1         bytes
+//   dw 0x1234, label       big endian words
+//   sprite "..XX..XX"      sprite rows, 8 or 16 pixels each, X/#/1 set
+//
+// Mnemonics are the ones printed by chip8-disasm, including SCHIP and XO-CHIP.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_with_layout(source, MemoryLayout::for_platform(Platform::XoChip))
+}
+
+// Assemble source into a ROM for the program start and memory of the layout
+pub fn assemble_with_layout(source: &str, layout: MemoryLayout) -> Result<Vec<u8>, AsmError> {
+    let start = layout.program_start;
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = start;
+
+    // First pass: define symbols and lay out the statements
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let at_line = |mut error: AsmError| {
+            error.line = line;
+            error
+        };
+        let mut lexemes = lex(text).map_err(at_line)?;
+
+        if let [Lexeme {
+            token: Token::Ident(name),
+            column,
+        }, Lexeme {
+            token: Token::Colon,
+            ..
+        }, ..] = lexemes.as_slice()
+        {
+            define(&mut symbols, name, address as i64, *column).map_err(at_line)?;
+            lexemes.drain(..2);
+        }
+
+        if let [Lexeme {
+            token: Token::Ident(name),
+            column,
+        }, Lexeme {
+            token: Token::Equals,
+            column: equals,
+        }, rest @ ..] = lexemes.as_slice()
+        {
+            let value = evaluate(rest, *equals + 1, &symbols).map_err(at_line)?;
+            define(&mut symbols, name, value, *column).map_err(at_line)?;
+            continue;
+        }
+
+        let (mnemonic, column) = match lexemes.first() {
+            Some(Lexeme {
+                token: Token::Ident(mnemonic),
+                column,
+            }) => (mnemonic.to_uppercase(), *column),
+            Some(lexeme) => return Err(at_line(error(lexeme.column, "expected a mnemonic"))),
+            None => continue,
+        };
+        let operands = split_operands(&lexemes[1..]);
+        let statement = Statement {
+            line,
+            address,
+            mnemonic,
+            column,
+            operands,
+        };
+
+        address += statement_size(&statement).map_err(at_line)?;
+        if address - start > layout.max_program_size() {
+            return Err(at_line(error(column, "program does not fit into memory")));
+        }
+        statements.push(statement);
+    }
+
+    // Second pass: all symbols are known, emit the bytes
+    let mut rom = Vec::with_capacity(address - start);
+    for statement in &statements {
+        let bytes = emit(statement, &symbols).map_err(|mut error| {
+            error.line = statement.line;
+            error
+        })?;
+        debug_assert_eq!(rom.len() + start, statement.address);
+        rom.extend(bytes);
+    }
+    Ok(rom)
+}
+
+fn define(
+    symbols: &mut HashMap<String, i64>,
+    name: &str,
+    value: i64,
+    column: usize,
+) -> Result<(), AsmError> {
+    if register(name).is_some() || keyword(name).is_some() {
+        return Err(error(column, format!("'{}' is a reserved name", name)));
+    }
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(error(column, format!("'{}' is already defined", name)));
+    }
+    Ok(())
+}
+
+fn lex(text: &str) -> Result<Vec<Lexeme>, AsmError> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$';
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let token = match c {
+            ';' => break,
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '=' => Token::Equals,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '[' => Token::Open,
+            ']' => Token::Close,
+            '"' => {
+                let length = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .ok_or_else(|| error(column, "unterminated string"))?;
+                let text = chars[i + 1..i + 1 + length].iter().collect();
+                i += length + 1;
+                Token::Text(text)
+            }
+            _ if is_word(c) => {
+                let length = chars[i..].iter().take_while(|&&c| is_word(c)).count();
+                let word: String = chars[i..i + length].iter().collect();
+                i += length - 1;
+                if c.is_ascii_digit() || c == '$' {
+                    match parse_number(&word) {
+                        Some(value) => Token::Number(value),
+                        None => return Err(error(column, format!("invalid number '{}'", word))),
+                    }
+                } else {
+                    Token::Ident(word)
+                }
+            }
+            _ => return Err(error(column, format!("unexpected character '{}'", c))),
+        };
+        lexemes.push(Lexeme { token, column });
+        i += 1;
+    }
+    Ok(lexemes)
+}
+
+// Decimal, hex as 0x1F or $1F, binary as 0b0101
+fn parse_number(word: &str) -> Option<i64> {
+    let lower = word.to_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn split_operands(lexemes: &[Lexeme]) -> Vec<Operand> {
+    let mut operands = Vec::new();
+    if lexemes.is_empty() {
+        return operands;
+    }
+    let mut column = lexemes[0].column;
+    let mut current = Vec::new();
+    for lexeme in lexemes {
+        if lexeme.token == Token::Comma {
+            operands.push(Operand {
+                column,
+                lexemes: std::mem::take(&mut current),
+            });
+            column = lexeme.column + 1;
+        } else {
+            if current.is_empty() {
+                column = lexeme.column;
+            }
+            current.push(lexeme.clone());
+        }
+    }
+    operands.push(Operand {
+        column,
+        lexemes: current,
+    });
+    operands
+}
+
+fn register(name: &str) -> Option<u8> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|x| x as u8),
+        _ => None,
+    }
+}
+
+fn keyword(name: &str) -> Option<Arg> {
+    match name.to_uppercase().as_str() {
+        "I" => Some(Arg::I),
+        "DT" => Some(Arg::Dt),
+        "ST" => Some(Arg::St),
+        "K" => Some(Arg::K),
+        "F" => Some(Arg::F),
+        "B" => Some(Arg::B),
+        "HF" => Some(Arg::Hf),
+        "R" => Some(Arg::R),
+        _ => None,
+    }
+}
+
+fn is_long(operand: &Operand) -> bool {
+    matches!(operand.lexemes.first(), Some(Lexeme { token: Token::Ident(name), .. }) if name.eq_ignore_ascii_case("LONG"))
+}
+
+// Size in bytes, known before the symbols are
+fn statement_size(statement: &Statement) -> Result<usize, AsmError> {
+    match statement.mnemonic.as_str() {
+        "DB" => Ok(statement.operands.len()),
+        "DW" => Ok(statement.operands.len() * 2),
+        "SPRITE" => {
+            let mut size = 0;
+            for operand in &statement.operands {
+                size += sprite_row(operand)?.len();
+            }
+            Ok(size)
+        }
+        "LD" if statement.operands.iter().any(is_long) => Ok(4),
+        _ => Ok(2),
+    }
+}
+
+// Pixels of a sprite literal, 8 per byte
+fn sprite_row(operand: &Operand) -> Result<Vec<u8>, AsmError> {
+    let text = match operand.lexemes.as_slice() {
+        [Lexeme {
+            token: Token::Text(text),
+            ..
+        }] => text,
+        _ => return Err(error(operand.column, "expected a sprite string")),
+    };
+    if text.is_empty() || text.chars().count() % 8 != 0 {
+        return Err(error(operand.column, "sprite rows are 8 or 16 pixels wide"));
+    }
+    let mut bytes = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    for pixels in chars.chunks(8) {
+        let mut byte = 0;
+        for &pixel in pixels {
+            let set = match pixel {
+                'X' | 'x' | '#' | '1' => 1,
+                '.' | ' ' | '0' => 0,
+                _ => {
+                    let message = format!("invalid sprite pixel '{}'", pixel);
+                    return Err(error(operand.column, message));
+                }
+            };
+            byte = byte << 1 | set;
+        }
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+// Sum of numbers and symbols, e.g. "sprites + 5 - offset"
+fn evaluate(
+    lexemes: &[Lexeme],
+    column: usize,
+    symbols: &HashMap<String, i64>,
+) -> Result<i64, AsmError> {
+    if lexemes.is_empty() {
+        return Err(error(column, "missing value"));
+    }
+    let mut value = 0;
+    let mut sign = 1;
+    let mut expect_term = true;
+    for lexeme in lexemes {
+        match (&lexeme.token, expect_term) {
+            (Token::Number(number), true) => value += sign * number,
+            (Token::Ident(name), true) => match symbols.get(name) {
+                Some(number) => value += sign * number,
+                None => return Err(error(lexeme.column, format!("unknown symbol '{}'", name))),
+            },
+            (Token::Minus, true) => {
+                sign = -sign;
+                continue;
+            }
+            (Token::Plus, false) => sign = 1,
+            (Token::Minus, false) => sign = -1,
+            _ => return Err(error(lexeme.column, "invalid expression")),
+        }
+        expect_term = !expect_term;
+    }
+    if expect_term {
+        return Err(error(
+            lexemes[lexemes.len() - 1].column,
+            "incomplete expression",
+        ));
+    }
+    Ok(value)
+}
+
+fn resolve(operand: &Operand, symbols: &HashMap<String, i64>) -> Result<Arg, AsmError> {
+    match operand.lexemes.as_slice() {
+        [Lexeme {
+            token: Token::Ident(name),
+            ..
+        }] if register(name).is_some() => Ok(Arg::V(register(name).unwrap())),
+        [Lexeme {
+            token: Token::Ident(name),
+            ..
+        }] if keyword(name).is_some() => Ok(keyword(name).unwrap()),
+        [Lexeme {
+            token: Token::Open, ..
+        }, Lexeme {
+            token: Token::Ident(name),
+            ..
+        }, Lexeme {
+            token: Token::Close,
+            ..
+        }] if name.eq_ignore_ascii_case("I") => Ok(Arg::IndirectI),
+        [long, rest @ ..] if is_long(operand) => {
+            evaluate(rest, long.column + 4, symbols).map(Arg::Long)
+        }
+        lexemes => evaluate(lexemes, operand.column, symbols).map(Arg::Value),
+    }
+}
+
+fn in_range(value: i64, min: i64, max: i64, column: usize, what: &str) -> Result<i64, AsmError> {
+    if value < min || value > max {
+        return Err(error(column, format!("{} {} out of range", what, value)));
+    }
+    Ok(value)
+}
+
+fn address(value: i64, column: usize) -> Result<u16, AsmError> {
+    in_range(value, 0, 0xFFF, column, "address").map(|v| v as u16)
+}
+
+// Negative bytes are stored as two's complement, e.g. ADD V0, -1
+fn byte(value: i64, column: usize) -> Result<u8, AsmError> {
+    in_range(value, -0x80, 0xFF, column, "byte").map(|v| v as u8)
+}
+
+fn nibble(value: i64, column: usize) -> Result<u8, AsmError> {
+    in_range(value, 0, 0xF, column, "nibble").map(|v| v as u8)
+}
+
+fn word(value: i64, column: usize) -> Result<u16, AsmError> {
+    in_range(value, -0x8000, 0xFFFF, column, "word").map(|v| v as u16)
+}
+
+fn emit(statement: &Statement, symbols: &HashMap<String, i64>) -> Result<Vec<u8>, AsmError> {
+    let mut bytes = Vec::new();
+    match statement.mnemonic.as_str() {
+        "DB" => {
+            for operand in &statement.operands {
+                let value = evaluate(&operand.lexemes, operand.column, symbols)?;
+                bytes.push(byte(value, operand.column)?);
+            }
+            return Ok(bytes);
+        }
+        "DW" => {
+            for operand in &statement.operands {
+                let value = evaluate(&operand.lexemes, operand.column, symbols)?;
+                bytes.extend(word(value, operand.column)?.to_be_bytes());
+            }
+            return Ok(bytes);
+        }
+        "SPRITE" => {
+            for operand in &statement.operands {
+                bytes.extend(sprite_row(operand)?);
+            }
+            return Ok(bytes);
+        }
+        _ => {}
+    }
+
+    let mut args = Vec::new();
+    for operand in &statement.operands {
+        args.push((resolve(operand, symbols)?, operand.column));
+    }
+
+    let instruction = match (statement.mnemonic.as_str(), args.as_slice()) {
+        // CHIP-8
+        ("SYS", [(Arg::Value(a), c)]) => Instruction::Sys {
+            nnn: address(*a, *c)?,
+        },
+        ("CLS", []) => Instruction::Cls,
+        ("RET", []) => Instruction::Ret,
+        ("JP", [(Arg::Value(a), c)]) => Instruction::Jump {
+            nnn: address(*a, *c)?,
+        },
+        ("JP", [(Arg::V(0), _), (Arg::Value(a), c)]) => Instruction::JumpOffset {
+            nnn: address(*a, *c)?,
+        },
+        ("CALL", [(Arg::Value(a), c)]) => Instruction::Call {
+            nnn: address(*a, *c)?,
+        },
+        ("SE", [(Arg::V(x), _), (Arg::Value(kk), c)]) => Instruction::SkipEqImm {
+            x: *x,
+            kk: byte(*kk, *c)?,
+        },
+        ("SE", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::SkipEqReg { x: *x, y: *y },
+        ("SNE", [(Arg::V(x), _), (Arg::Value(kk), c)]) => Instruction::SkipNeImm {
+            x: *x,
+            kk: byte(*kk, *c)?,
+        },
+        ("SNE", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::SkipNeReg { x: *x, y: *y },
+        ("LD", [(Arg::V(x), _), (Arg::Value(kk), c)]) => Instruction::LoadImm {
+            x: *x,
+            kk: byte(*kk, *c)?,
+        },
+        ("LD", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::Move { x: *x, y: *y },
+        ("LD", [(Arg::I, _), (Arg::Value(a), c)]) => Instruction::LoadI {
+            nnn: address(*a, *c)?,
+        },
+        ("LD", [(Arg::V(x), _), (Arg::Dt, _)]) => Instruction::LoadDelay { x: *x },
+        ("LD", [(Arg::V(x), _), (Arg::K, _)]) => Instruction::WaitKey { x: *x },
+        ("LD", [(Arg::Dt, _), (Arg::V(x), _)]) => Instruction::SetDelay { x: *x },
+        ("LD", [(Arg::St, _), (Arg::V(x), _)]) => Instruction::SetSound { x: *x },
+        ("LD", [(Arg::F, _), (Arg::V(x), _)]) => Instruction::LoadFont { x: *x },
+        ("LD", [(Arg::B, _), (Arg::V(x), _)]) => Instruction::StoreBcd { x: *x },
+        ("LD", [(Arg::IndirectI, _), (Arg::V(x), _)]) => Instruction::Store { x: *x },
+        ("LD", [(Arg::V(x), _), (Arg::IndirectI, _)]) => Instruction::Load { x: *x },
+        ("ADD", [(Arg::V(x), _), (Arg::Value(kk), c)]) => Instruction::AddImm {
+            x: *x,
+            kk: byte(*kk, *c)?,
+        },
+        ("ADD", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::AddReg { x: *x, y: *y },
+        ("ADD", [(Arg::I, _), (Arg::V(x), _)]) => Instruction::AddI { x: *x },
+        ("OR", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::Or { x: *x, y: *y },
+        ("AND", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::And { x: *x, y: *y },
+        ("XOR", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::Xor { x: *x, y: *y },
+        ("SUB", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::Sub { x: *x, y: *y },
+        ("SUBN", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::SubReverse { x: *x, y: *y },
+        // A single register shifts in place with and without the shift quirk
+        ("SHR", [(Arg::V(x), _)]) => Instruction::ShiftRight { x: *x, y: *x },
+        ("SHR", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::ShiftRight { x: *x, y: *y },
+        ("SHL", [(Arg::V(x), _)]) => Instruction::ShiftLeft { x: *x, y: *x },
+        ("SHL", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::ShiftLeft { x: *x, y: *y },
+        ("RND", [(Arg::V(x), _), (Arg::Value(kk), c)]) => Instruction::Random {
+            x: *x,
+            kk: byte(*kk, *c)?,
+        },
+        ("DRW", [(Arg::V(x), _), (Arg::V(y), _), (Arg::Value(n), c)]) => Instruction::Draw {
+            x: *x,
+            y: *y,
+            n: nibble(*n, *c)?,
+        },
+        ("SKP", [(Arg::V(x), _)]) => Instruction::SkipKey { x: *x },
+        ("SKNP", [(Arg::V(x), _)]) => Instruction::SkipNotKey { x: *x },
+        // SCHIP
+        ("SCD", [(Arg::Value(n), c)]) => Instruction::ScrollDown { n: nibble(*n, *c)? },
+        ("SCR", []) => Instruction::ScrollRight,
+        ("SCL", []) => Instruction::ScrollLeft,
+        ("EXIT", []) => Instruction::Exit,
+        ("LOW", []) => Instruction::LowRes,
+        ("HIGH", []) => Instruction::HighRes,
+        ("LD", [(Arg::Hf, _), (Arg::V(x), _)]) => Instruction::LoadBigFont { x: *x },
+        ("LD", [(Arg::R, _), (Arg::V(x), _)]) => Instruction::StoreFlags { x: *x },
+        ("LD", [(Arg::V(x), _), (Arg::R, _)]) => Instruction::LoadFlags { x: *x },
+        // XO-CHIP
+        ("SCU", [(Arg::Value(n), c)]) => Instruction::ScrollUp { n: nibble(*n, *c)? },
+        ("SAVE", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::SaveRange { x: *x, y: *y },
+        ("LOAD", [(Arg::V(x), _), (Arg::V(y), _)]) => Instruction::LoadRange { x: *x, y: *y },
+        ("LD", [(Arg::I, _), (Arg::Long(a), c)]) => {
+            let nnnn = in_range(*a, 0, 0xFFFF, *c, "address")? as u16;
+            bytes.extend(Instruction::LoadILong.encode().to_be_bytes());
+            bytes.extend(nnnn.to_be_bytes());
+            return Ok(bytes);
+        }
+        ("PLANE", [(Arg::Value(n), c)]) => Instruction::Plane { n: nibble(*n, *c)? },
+        ("AUDIO", []) => Instruction::Audio,
+        ("PITCH", [(Arg::V(x), _)]) => Instruction::Pitch { x: *x },
+        (mnemonic, _) if MNEMONICS.contains(&mnemonic) => {
+            let message = format!("invalid operands for {}", mnemonic);
+            return Err(error(statement.column, message));
+        }
+        (mnemonic, _) => {
+            let message = format!("unknown mnemonic '{}'", mnemonic);
+            return Err(error(statement.column, message));
+        }
+    };
+    bytes.extend(instruction.encode().to_be_bytes());
+    Ok(bytes)
+}
@@ -0,0 +1,82 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(test)]
+#[path = "./tests/browser.rs"]
+mod tests;
+
+// File extensions of CHIP-8, SCHIP and XO-CHIP programs and Octo cartridges
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "sc8", "xo8", "gif"];
+
+pub struct RomEntry {
+    pub path: PathBuf,
+    // File name without the extension
+    pub name: String,
+    // Contents of the .txt file next to the ROM, if there is one
+    pub description: Option<String>,
+}
+
+// ROM files of a directory for a frontend to pick from
+pub struct RomBrowser {
+    pub dir: PathBuf,
+    pub entries: Vec<RomEntry>,
+    pub selected: usize,
+    // Shown along with the list, e.g. why a ROM didn't load
+    pub message: Option<String>,
+}
+
+impl RomBrowser {
+    // List the ROMs of a directory sorted by name
+    pub fn scan(dir: &Path) -> io::Result<RomBrowser> {
+        let mut entries = Vec::new();
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            let is_rom = path.extension().is_some_and(|extension| {
+                let extension = extension.to_string_lossy().to_lowercase();
+                ROM_EXTENSIONS.contains(&extension.as_str())
+            });
+            if !is_rom || !path.is_file() {
+                continue;
+            }
+            entries.push(RomEntry {
+                name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                description: fs::read_to_string(path.with_extension("txt")).ok(),
+                path,
+            });
+        }
+        entries.sort_by_key(|entry| entry.name.to_lowercase());
+
+        Ok(RomBrowser {
+            dir: dir.to_path_buf(),
+            entries,
+            selected: 0,
+            message: None,
+        })
+    }
+
+    pub fn selected(&self) -> Option<&RomEntry> {
+        self.entries.get(self.selected)
+    }
+
+    // Move the selection, wrapping around at both ends
+    pub fn select_next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+        }
+    }
+
+    // Select the entry of a path, e.g. the ROM that was running before
+    pub fn select_path(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        }
+    }
+}
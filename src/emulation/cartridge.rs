@@ -0,0 +1,162 @@
+use std::{fmt, fs::File, io, io::Read};
+
+use serde_json::Value;
+
+use super::{asm::AsmError, octo, parse_colour, Platform, Quirks, DEFAULT_INSTRUCTIONS_PER_FRAME};
+
+#[cfg(test)]
+#[path = "./tests/cartridge.rs"]
+mod tests;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    // The file could not be read
+    Io(io::Error),
+    // The file is not a valid GIF image
+    Gif(String),
+    // The image holds no cartridge payload
+    Payload(String),
+    // The Octo source of the program doesn't compile
+    Compile(AsmError),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(err) => write!(f, "cartridge could not be read: {}", err),
+            CartridgeError::Gif(err) => write!(f, "cartridge is not a GIF image: {}", err),
+            CartridgeError::Payload(err) => write!(f, "cartridge payload is invalid: {}", err),
+            CartridgeError::Compile(err) => write!(f, "cartridge program invalid: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+    fn from(err: io::Error) -> Self {
+        CartridgeError::Io(err)
+    }
+}
+
+impl From<gif::DecodingError> for CartridgeError {
+    fn from(err: gif::DecodingError) -> Self {
+        CartridgeError::Gif(err.to_string())
+    }
+}
+
+// Program and settings of an Octo GIF cartridge.
+//
+// Octo hides a JSON payload {"program": ..., "options": {...}} in the two low bits
+// of every pixel of every frame, four pixels per byte, behind a 32 bit length.
+// The program is Octo source code, it is compiled into the ROM.
+// The options translate to the emulator configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cartridge {
+    // Octo source code of the program
+    pub source: String,
+    // The compiled program, loaded at 0x200
+    pub rom: Vec<u8>,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    // Background, plane 1, plane 2 and both planes as 0xRRGGBB
+    pub colours: Option<[u32; 4]>,
+}
+
+impl Cartridge {
+    pub fn load(file_path: &str) -> Result<Cartridge, CartridgeError> {
+        let mut bytes = Vec::new();
+        File::open(file_path)?.read_to_end(&mut bytes)?;
+        Cartridge::decode(&bytes)
+    }
+
+    pub fn decode(gif_bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let payload = extract_payload(gif_bytes)?;
+        let json: Value = serde_json::from_str(&payload)
+            .map_err(|err| CartridgeError::Payload(err.to_string()))?;
+
+        let source = match json.get("program").and_then(Value::as_str) {
+            Some(source) => source.to_string(),
+            None => return Err(CartridgeError::Payload("no program".to_string())),
+        };
+        let rom = octo::compile(&source).map_err(CartridgeError::Compile)?;
+        let options = json.get("options").cloned().unwrap_or(Value::Null);
+        let flag = |name: &str| options.get(name).and_then(Value::as_bool).unwrap_or(false);
+        let colour = |name: &str| {
+            options
+                .get(name)
+                .and_then(Value::as_str)
+                .and_then(parse_colour)
+        };
+
+        // The memory Octo compiles for tells the platform apart
+        let platform = match options.get("maxSize").and_then(Value::as_u64) {
+            Some(size) if size > 3583 => Platform::XoChip,
+            Some(size) if size > 3216 => Platform::SuperChip,
+            _ => Platform::Chip8,
+        };
+
+        // Octo's quirks are named after the interpreters that have them,
+        // shift and load/store quirks mean Vy and I are left alone
+        let quirks = Quirks {
+            shift_uses_vy: !flag("shiftQuirks"),
+            load_store_increments_i: !flag("loadStoreQuirks"),
+            load_store_increments_i_by_x: false,
+            jump_uses_vx: flag("jumpQuirks"),
+            vf_reset: flag("logicQuirks"),
+            clip_sprites: flag("clipQuirks"),
+            display_wait: flag("vBlankQuirks"),
+        };
+
+        let instructions_per_frame = options
+            .get("tickrate")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_INSTRUCTIONS_PER_FRAME, |rate| rate as u32);
+
+        let colours = match (
+            colour("backgroundColor"),
+            colour("fillColor"),
+            colour("fillColor2"),
+            colour("blendColor"),
+        ) {
+            (Some(back), Some(fill), Some(fill2), Some(blend)) => Some([back, fill, fill2, blend]),
+            _ => None,
+        };
+
+        Ok(Cartridge {
+            source,
+            rom,
+            platform,
+            quirks,
+            instructions_per_frame,
+            colours,
+        })
+    }
+}
+
+// Collect the two low bits of the palette index of every pixel
+fn extract_payload(gif_bytes: &[u8]) -> Result<String, CartridgeError> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif_bytes)?;
+
+    let mut pixels = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        pixels.extend_from_slice(&frame.buffer);
+    }
+
+    let bytes: Vec<u8> = pixels
+        .chunks_exact(4)
+        .map(|p| (p[0] & 3) << 6 | (p[1] & 3) << 4 | (p[2] & 3) << 2 | (p[3] & 3))
+        .collect();
+    if bytes.len() < 4 {
+        return Err(CartridgeError::Payload("image too small".to_string()));
+    }
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let data = match bytes.get(4..4 + size) {
+        Some(data) => data,
+        None => return Err(CartridgeError::Payload("truncated payload".to_string())),
+    };
+    String::from_utf8(data.to_vec()).map_err(|err| CartridgeError::Payload(err.to_string()))
+}
@@ -1,12 +1,18 @@
-use super::Disk;
-use rand::Rng;
+use std::fmt;
+
+use super::{
+    Disk, DiskError, Framebuffer, Instruction, LayoutError, MemoryLayout, Platform, Quirks,
+    Resolution,
+};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
 
 #[cfg(test)]
 #[path = "./tests/cpu.rs"]
 mod tests;
 
-const CPU_DEBUG_PRINT: bool = true;
-const CPU_DEBUG_PRINT_VIDEO_RAM: bool = false;
+// Opcode printing is for debugging, a library stays quiet on stdout by default
+const CPU_DEBUG_PRINT: bool = false;
+const CPU_DEBUG_PRINT_FRAMEBUFFER: bool = false;
 
 const FONT_SET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -27,11 +33,66 @@ const FONT_SET: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SCHIP 8x10 font
+const BIG_FONT_SET: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    // Opcode at address could not be decoded
+    UnknownOpcode { opcode: u16, address: u16 },
+    // Call with all 16 stack slots in use
+    StackOverflow { address: u16 },
+    // Return with an empty stack
+    StackUnderflow { address: u16 },
+    // Memory access outside of ram by the instruction at pc
+    MemoryFault { address: usize, pc: u16 },
+    // Program counter left the addressable memory
+    PcOutOfRange { pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode { opcode, address } => {
+                write!(f, "unknown opcode {:04x} at {:04x}", opcode, address)
+            }
+            CpuError::StackOverflow { address } => write!(f, "stack overflow at {:04x}", address),
+            CpuError::StackUnderflow { address } => {
+                write!(f, "stack underflow at {:04x}", address)
+            }
+            CpuError::MemoryFault { address, pc } => {
+                write!(f, "memory fault accessing {:04x} at {:04x}", address, pc)
+            }
+            CpuError::PcOutOfRange { pc } => write!(f, "program counter out of range: {:04x}", pc),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
 pub struct Cpu {
     // Memory access
-    pub video_ram: [[u8; 64]; 32],
-    pub video_ram_changed: bool,
-    ram: [u8; 4096],
+    pub framebuffer: Framebuffer,
+    // Bitmask of the framebuffer planes drawn and cleared
+    pub planes: u8,
+    ram: Vec<u8>,
     // Registers
     pub reg_v: [u8; 16],
     pub reg_i: u16,
@@ -39,7 +100,6 @@ pub struct Cpu {
     pub reg_sp: u8,
     // Stack
     pub stack: [u16; 16],
-    pub stack_ptr: u8,
     // Opcodes
     pub opcode: u16,
     pub opcode_last: u16,
@@ -48,15 +108,55 @@ pub struct Cpu {
     pub reg_sound_timer: u8,
     // Keyboard
     pub keyboard: [bool; 16],
+    // Interpreter variant
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub layout: MemoryLayout,
+    vblank_wait: bool,
+    // SCHIP
+    pub rpl_flags: [u8; 16],
+    pub exited: bool,
+    // XO-CHIP audio, stored for a future sound implementation
+    pub audio_pattern: [u8; 16],
+    pub audio_pitch: u8,
+    // Random numbers for Cxkk, replace or seed it for reproducible runs
+    pub rng: Box<dyn RngCore>,
+    // Print every executed opcode for debugging
+    pub print_opcodes: bool,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
+#[allow(non_snake_case)]
 impl Cpu {
     pub fn new() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Self {
+        Self::with_platform(Platform::Chip8, quirks)
+    }
+
+    pub fn with_platform(platform: Platform, quirks: Quirks) -> Self {
+        Self::with_layout(platform, quirks, MemoryLayout::for_platform(platform))
+            .expect("default memory layout is valid")
+    }
+
+    pub fn with_layout(
+        platform: Platform,
+        quirks: Quirks,
+        layout: MemoryLayout,
+    ) -> Result<Self, LayoutError> {
+        layout.validate()?;
         let mut cpu = Cpu {
             // Memory access
-            video_ram: [[0; 64]; 32],
-            video_ram_changed: true,
-            ram: [0; 4096],
+            framebuffer: Framebuffer::new(Resolution::Lores),
+            planes: 1,
+            ram: vec![0; layout.ram_size],
             // Registers
             reg_v: [0; 16],
             reg_i: 0,
@@ -64,7 +164,6 @@ impl Cpu {
             reg_sp: 0,
             // Stack
             stack: [0; 16],
-            stack_ptr: 0,
             // Opcodes
             opcode: 0,
             opcode_last: 0,
@@ -73,348 +172,538 @@ impl Cpu {
             reg_sound_timer: 0,
             // Keyboard
             keyboard: [false; 16],
+            // Interpreter variant
+            platform,
+            quirks,
+            layout,
+            vblank_wait: false,
+            // SCHIP
+            rpl_flags: [0; 16],
+            exited: false,
+            // XO-CHIP audio
+            audio_pattern: [0; 16],
+            audio_pitch: 64,
+            // Random numbers
+            rng: Box::new(StdRng::from_entropy()),
+            print_opcodes: CPU_DEBUG_PRINT,
         };
 
-        cpu.reg_pc = 0x200;
+        cpu.reg_pc = layout.program_start as u16;
+
+        let font = layout.font_address;
+        cpu.ram[font..font + FONT_SET.len()].copy_from_slice(&FONT_SET);
+        let big_font = layout.big_font_address;
+        cpu.ram[big_font..big_font + BIG_FONT_SET.len()].copy_from_slice(&BIG_FONT_SET);
+        Ok(cpu)
+    }
+
+    // Power cycle the machine: registers, ram and screen start over, the platform,
+    // quirks, memory layout, random number generator and opcode printing are kept
+    pub fn reset(&mut self) {
+        let fresh = Cpu::with_layout(self.platform, self.quirks, self.layout)
+            .expect("memory layout was validated");
+        let rng = std::mem::replace(&mut self.rng, fresh.rng);
+        let print_opcodes = self.print_opcodes;
+        *self = Cpu {
+            rng,
+            print_opcodes,
+            ..fresh
+        };
+    }
 
-        for i in 0..FONT_SET.len() {
-            cpu.ram[i] = FONT_SET[i];
-        }
-        return cpu;
+    // Restart the random number sequence from a seed
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
     }
 
-    pub fn load_disk_to_ram(&mut self, disk: &Disk) {
-        for i in 0..disk.size {
-            self.ram[i as usize + 0x200] = disk.rom[i as usize];
+    // Copy the program to the program start, refusing programs that don't fit into ram
+    pub fn load_disk_to_ram(&mut self, disk: &Disk) -> Result<(), DiskError> {
+        let max = self.layout.max_program_size();
+        if disk.size > max {
+            return Err(DiskError::TooLarge {
+                size: disk.size,
+                max,
+            });
         }
-        //TODO: someone we need to do a disk size check because of the +0x200
-        println!("Loaded {} bytes to RAM", disk.size);
+        let start = self.layout.program_start;
+        self.ram[start..start + disk.size].copy_from_slice(&disk.rom[..disk.size]);
+        Ok(())
     }
 
+    // Keypad keys 0x0 to 0xF
     pub fn key_pressed(&mut self, key: u8) {
-        self.keyboard[key as usize - 1] = true;
-        println!("Key pressed: {}", key);
+        self.keyboard[(key & 0xF) as usize] = true;
+    }
+
+    pub fn key_released(&mut self, key: u8) {
+        self.keyboard[(key & 0xF) as usize] = false;
     }
 
-    pub fn next(&mut self) {
-        self.opcode_last = self.opcode;
+    // Signal the start of a new frame to a cpu waiting for the display
+    pub fn vblank(&mut self) {
+        self.vblank_wait = false;
+    }
 
-        if self.reg_pc == 0x1000 {
-            //TODO: Overflow check
-            return;
+    // Fetch and execute a single instruction
+    pub fn step(&mut self) -> Result<(), CpuError> {
+        if self.reg_pc as usize + 1 >= self.ram.len() {
+            return Err(CpuError::PcOutOfRange { pc: self.reg_pc });
         }
 
-        // Run opcode
-        self.opcode = (self.ram[self.reg_pc as usize] as u16) << 8
-            | self.ram[(self.reg_pc + 1) as usize] as u16;
-        self.reg_pc += 2;
-        self.execute();
+        // Run opcode unless a draw is waiting for the next frame or the program exited
+        if !self.vblank_wait && !self.exited {
+            self.opcode_last = self.opcode;
+            self.opcode = self.read_word(self.reg_pc as usize);
+            self.reg_pc = self.reg_pc.wrapping_add(2);
+            self.execute()?;
+        }
+        Ok(())
+    }
 
-        // Handle timers
+    // Count down the delay and sound timers, called at 60 Hz
+    pub fn tick_timers(&mut self) {
         if self.reg_delay_timer > 0 {
-            self.reg_delay_timer = self.reg_delay_timer - 1;
+            self.reg_delay_timer -= 1;
         }
 
         if self.reg_sound_timer > 0 {
-            self.reg_sound_timer = self.reg_sound_timer - 1;
-        }
-    }
-
-    fn execute(&mut self) {
-        // if self.opcode != 0 {
-        //     println!("Opcode: {:04x}", self.opcode);
-        // }
-
-        match self.opcode & 0xF000 {
-            0x0000 => match self.opcode & 0x0FFF {
-                0x00E0 => self.op_0x00e0(),
-                0x00EE => self.op_0x00ee(),
-                _ => (), // Noop //TODO handle 0NNN calls
-            },
-            0x1000 => self.op_0x1nnn(),
-            0x2000 => self.op_0x2nnn(),
-            0x3000 => self.op_0x3xkk(),
-            0x4000 => self.op_0x4xkk(),
-            0x5000 => self.op_0x5xy0(),
-            0x6000 => self.op_0x6xkk(),
-            0x7000 => self.op_0x7xkk(),
-            0x8000 => match self.opcode & 0x000F {
-                0x0000 => self.op_0x8xy0(),
-                0x0001 => self.op_0x8xy1(),
-                0x0002 => self.op_0x8xy2(),
-                0x0003 => self.op_0x8xy3(),
-                0x0004 => self.op_0x8xy4(),
-                0x0005 => self.op_0x8xy5(),
-                0x0006 => self.op_0x8xy6(),
-                0x0007 => self.op_0x8xy7(),
-                0x000E => self.op_0x8xyE(),
-                _ => println!("0x8000 Unknown opcode: {:04x}", self.opcode),
-            },
-            0x9000 => self.op_0x9xy0(),
-            0xA000 => self.op_0xAnnn(),
-            0xB000 => self.op_0xBnnn(),
-            0xC000 => self.op_0xCxkk(),
-            0xD000 => self.op_0xDxyn(),
-            0xE000 => match self.opcode & 0x000F {
-                0x000E => self.op_0xEx9E(),
-                0x0001 => self.op_0xExA1(),
-                _ => println!("0xE000 Unknown opcode: {:04x}", self.opcode),
-            },
-            0xF000 => match self.opcode & 0x00FF {
-                0x0007 => self.op_0xFx07(),
-                0x000A => self.op_0xFx0A(),
-                0x0015 => self.op_0xFx15(),
-                0x0018 => self.op_0xFx18(),
-                0x001E => self.op_0xFx1E(),
-                0x0029 => self.op_0xFx29(),
-                0x0033 => self.op_0xFx33(),
-                0x0055 => self.op_0xFx55(),
-                0x0065 => self.op_0xFx65(),
-                _ => println!("0xF000 Unknown opcode: {:04x}", self.opcode),
-            },
-            _ => println!("Unknown opcode: {:04x}", self.opcode),
-        }
-    }
-
-    // Clear display
-    fn op_0x00e0(&mut self) {
-        for i in 0..64 {
-            for j in 0..32 {
-                self.video_ram[j][i] = 0;
+            self.reg_sound_timer -= 1;
+        }
+    }
+
+    // Current screen size in pixels
+    pub fn screen_width(&self) -> usize {
+        self.framebuffer.width()
+    }
+
+    pub fn screen_height(&self) -> usize {
+        self.framebuffer.height()
+    }
+
+    // SCHIP high resolution mode
+    pub fn hires(&self) -> bool {
+        self.framebuffer.resolution() == Resolution::Hires
+    }
+
+    fn execute(&mut self) -> Result<(), CpuError> {
+        if self.print_opcodes {
+            println!("Opcode: {:04x}", self.opcode);
+        }
+        // Instructions of newer platforms are unknown to older ones
+        let instruction = match Instruction::decode_for(self.opcode, self.platform) {
+            Ok(instruction) => instruction,
+            Err(_) => return Err(self.unknown_opcode()),
+        };
+
+        match instruction {
+            Instruction::Sys { .. } => (), // Noop //TODO handle 0NNN calls
+            Instruction::Cls => self.op_0x00e0(),
+            Instruction::Ret => self.op_0x00ee()?,
+            Instruction::Jump { nnn } => self.op_0x1nnn(nnn),
+            Instruction::Call { nnn } => self.op_0x2nnn(nnn)?,
+            Instruction::SkipEqImm { x, kk } => self.op_0x3xkk(x as usize, kk),
+            Instruction::SkipNeImm { x, kk } => self.op_0x4xkk(x as usize, kk),
+            Instruction::SkipEqReg { x, y } => self.op_0x5xy0(x as usize, y as usize),
+            Instruction::LoadImm { x, kk } => self.op_0x6xkk(x as usize, kk),
+            Instruction::AddImm { x, kk } => self.op_0x7xkk(x as usize, kk),
+            Instruction::Move { x, y } => self.op_0x8xy0(x as usize, y as usize),
+            Instruction::Or { x, y } => self.op_0x8xy1(x as usize, y as usize),
+            Instruction::And { x, y } => self.op_0x8xy2(x as usize, y as usize),
+            Instruction::Xor { x, y } => self.op_0x8xy3(x as usize, y as usize),
+            Instruction::AddReg { x, y } => self.op_0x8xy4(x as usize, y as usize),
+            Instruction::Sub { x, y } => self.op_0x8xy5(x as usize, y as usize),
+            Instruction::ShiftRight { x, y } => self.op_0x8xy6(x as usize, y as usize),
+            Instruction::SubReverse { x, y } => self.op_0x8xy7(x as usize, y as usize),
+            Instruction::ShiftLeft { x, y } => self.op_0x8xyE(x as usize, y as usize),
+            Instruction::SkipNeReg { x, y } => self.op_0x9xy0(x as usize, y as usize),
+            Instruction::LoadI { nnn } => self.op_0xAnnn(nnn),
+            Instruction::JumpOffset { nnn } => self.op_0xBnnn(nnn),
+            Instruction::Random { x, kk } => self.op_0xCxkk(x as usize, kk),
+            Instruction::Draw { x, y, n } => self.op_0xDxyn(x as usize, y as usize, n)?,
+            Instruction::SkipKey { x } => self.op_0xEx9E(x as usize),
+            Instruction::SkipNotKey { x } => self.op_0xExA1(x as usize),
+            Instruction::LoadDelay { x } => self.op_0xFx07(x as usize),
+            Instruction::WaitKey { x } => self.op_0xFx0A(x as usize),
+            Instruction::SetDelay { x } => self.op_0xFx15(x as usize),
+            Instruction::SetSound { x } => self.op_0xFx18(x as usize),
+            Instruction::AddI { x } => self.op_0xFx1E(x as usize),
+            Instruction::LoadFont { x } => self.op_0xFx29(x as usize),
+            Instruction::StoreBcd { x } => self.op_0xFx33(x as usize)?,
+            Instruction::Store { x } => self.op_0xFx55(x as usize)?,
+            Instruction::Load { x } => self.op_0xFx65(x as usize)?,
+            // SCHIP
+            Instruction::ScrollDown { n } => self.op_0x00cn(n),
+            Instruction::ScrollRight => self.op_0x00fb(),
+            Instruction::ScrollLeft => self.op_0x00fc(),
+            Instruction::Exit => self.op_0x00fd(),
+            Instruction::LowRes => self.op_0x00fe(),
+            Instruction::HighRes => self.op_0x00ff(),
+            Instruction::LoadBigFont { x } => self.op_0xFx30(x as usize),
+            Instruction::StoreFlags { x } => self.op_0xFx75(x as usize),
+            Instruction::LoadFlags { x } => self.op_0xFx85(x as usize),
+            // XO-CHIP
+            Instruction::ScrollUp { n } => self.op_0x00dn(n),
+            Instruction::SaveRange { x, y } => self.op_0x5xy2(x as usize, y as usize)?,
+            Instruction::LoadRange { x, y } => self.op_0x5xy3(x as usize, y as usize)?,
+            Instruction::LoadILong => self.op_0xF000()?,
+            Instruction::Plane { n } => self.op_0xFn01(n),
+            Instruction::Audio => self.op_0xF002()?,
+            Instruction::Pitch { x } => self.op_0xFx3A(x as usize),
+        }
+        Ok(())
+    }
+
+    // Address of the instruction currently being executed
+    fn opcode_address(&self) -> u16 {
+        self.reg_pc.wrapping_sub(2)
+    }
+
+    fn unknown_opcode(&self) -> CpuError {
+        CpuError::UnknownOpcode {
+            opcode: self.opcode,
+            address: self.opcode_address(),
+        }
+    }
+
+    // Big endian word at address, reading zero past the end of ram
+    fn read_word(&self, address: usize) -> u16 {
+        let high = self.ram.get(address).copied().unwrap_or(0) as u16;
+        let low = self.ram.get(address + 1).copied().unwrap_or(0) as u16;
+        high << 8 | low
+    }
+
+    // Skip the next instruction, including the 4 byte long F000 NNNN on XO-CHIP
+    fn skip(&mut self) {
+        let size = match Instruction::decode(self.read_word(self.reg_pc as usize)) {
+            Ok(next) if next.platform() <= self.platform => next.size(),
+            _ => 2,
+        };
+        self.reg_pc = self.reg_pc.wrapping_add(size);
+    }
+
+    fn read_ram(&self, address: usize) -> Result<u8, CpuError> {
+        match self.ram.get(address) {
+            Some(value) => Ok(*value),
+            None => Err(CpuError::MemoryFault {
+                address,
+                pc: self.opcode_address(),
+            }),
+        }
+    }
+
+    // Writes to the reserved interpreter area fault like writes past the end of ram
+    fn write_ram(&mut self, address: usize, value: u8) -> Result<(), CpuError> {
+        let pc = self.opcode_address();
+        if address < self.layout.reserved {
+            return Err(CpuError::MemoryFault { address, pc });
+        }
+        match self.ram.get_mut(address) {
+            Some(cell) => {
+                *cell = value;
+                Ok(())
             }
+            None => Err(CpuError::MemoryFault { address, pc }),
         }
-        self.video_ram_changed = true;
-        println!("cleared");
-        debug_print(self.opcode);
+    }
+
+    // Clear display (the selected planes only)
+    fn op_0x00e0(&mut self) {
+        self.framebuffer.clear(self.planes);
+    }
+
+    // Scroll display N lines down
+    fn op_0x00cn(&mut self, n: u8) {
+        self.scroll(0, n as isize);
+    }
+
+    // Scroll display N lines up
+    fn op_0x00dn(&mut self, n: u8) {
+        self.scroll(0, -(n as isize));
+    }
+
+    // Scroll display 4 pixels right
+    fn op_0x00fb(&mut self) {
+        self.scroll(4, 0);
+    }
+
+    // Scroll display 4 pixels left
+    fn op_0x00fc(&mut self) {
+        self.scroll(-4, 0);
+    }
+
+    // Move the selected planes by dx, dy pixels
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        self.framebuffer.scroll(dx, dy, self.planes);
+    }
+
+    // Exit the interpreter
+    fn op_0x00fd(&mut self) {
+        self.exited = true;
+    }
+
+    // Switch to low resolution
+    fn op_0x00fe(&mut self) {
+        self.framebuffer.set_resolution(Resolution::Lores);
+    }
+
+    // Switch to high resolution
+    fn op_0x00ff(&mut self) {
+        self.framebuffer.set_resolution(Resolution::Hires);
     }
 
     // Return from subroutine
-    fn op_0x00ee(&mut self) {
+    fn op_0x00ee(&mut self) -> Result<(), CpuError> {
+        if self.reg_sp == 0 {
+            return Err(CpuError::StackUnderflow {
+                address: self.opcode_address(),
+            });
+        }
         self.reg_sp -= 1;
         self.reg_pc = self.stack[self.reg_sp as usize];
-        debug_print(self.opcode);
+        Ok(())
     }
 
     // Jump to address NNN
-    fn op_0x1nnn(&mut self) {
-        self.reg_pc = self.opcode & 0x0FFF;
-        //debug_print(self.opcode);
+    fn op_0x1nnn(&mut self, nnn: u16) {
+        self.reg_pc = nnn;
     }
 
     // Call subroutine at NNN
-    fn op_0x2nnn(&mut self) {
+    fn op_0x2nnn(&mut self, nnn: u16) -> Result<(), CpuError> {
+        if self.reg_sp as usize >= self.stack.len() {
+            return Err(CpuError::StackOverflow {
+                address: self.opcode_address(),
+            });
+        }
         self.stack[self.reg_sp as usize] = self.reg_pc;
         self.reg_sp += 1;
-        self.reg_pc = self.opcode & 0x0FFF;
-        debug_print(self.opcode);
+        self.reg_pc = nnn;
+        Ok(())
     }
 
     // Skip next instruction if Vx = kk
-    fn op_0x3xkk(&mut self) {
-        if self.reg_v[((self.opcode & 0x0F00) >> 8) as usize] == (self.opcode & 0x00FF) as u8 {
-            self.reg_pc += 2;
+    fn op_0x3xkk(&mut self, reg_x: usize, kk: u8) {
+        if self.reg_v[reg_x] == kk {
+            self.skip();
         }
-        debug_print(self.opcode);
     }
 
     // Skip next instruction if Vx != kk
-    fn op_0x4xkk(&mut self) {
-        if self.reg_v[((self.opcode & 0x0F00) >> 8) as usize] != (self.opcode & 0x00FF) as u8 {
-            self.reg_pc += 2;
+    fn op_0x4xkk(&mut self, reg_x: usize, kk: u8) {
+        if self.reg_v[reg_x] != kk {
+            self.skip();
         }
-        debug_print(self.opcode);
     }
 
     // Skip next instruction if Vx = Vy
-    fn op_0x5xy0(&mut self) {
-        if self.reg_v[((self.opcode & 0x0F00) >> 8) as usize]
-            == self.reg_v[((self.opcode & 0x00F0) >> 4) as usize]
-        {
-            self.reg_pc += 2;
+    fn op_0x5xy0(&mut self, reg_x: usize, reg_y: usize) {
+        if self.reg_v[reg_x] == self.reg_v[reg_y] {
+            self.skip();
+        }
+    }
+
+    // Registers Vx to Vy in either order, as used by 5XY2 and 5XY3
+    fn register_range(reg_x: usize, reg_y: usize) -> Vec<usize> {
+        if reg_x <= reg_y {
+            (reg_x..=reg_y).collect()
+        } else {
+            (reg_y..=reg_x).rev().collect()
         }
-        debug_print(self.opcode);
+    }
+
+    // Stores registers Vx to Vy in memory starting at location I, I is not changed.
+    fn op_0x5xy2(&mut self, reg_x: usize, reg_y: usize) -> Result<(), CpuError> {
+        for (i, reg) in Self::register_range(reg_x, reg_y).into_iter().enumerate() {
+            self.write_ram(self.reg_i as usize + i, self.reg_v[reg])?;
+        }
+        Ok(())
+    }
+
+    // Fills registers Vx to Vy with values from memory starting at location I, I is not changed.
+    fn op_0x5xy3(&mut self, reg_x: usize, reg_y: usize) -> Result<(), CpuError> {
+        for (i, reg) in Self::register_range(reg_x, reg_y).into_iter().enumerate() {
+            self.reg_v[reg] = self.read_ram(self.reg_i as usize + i)?;
+        }
+        Ok(())
     }
 
     // Set Vx = kk
-    fn op_0x6xkk(&mut self) {
-        self.reg_v[((self.opcode & 0x0F00) >> 8) as usize] = (self.opcode & 0x00FF) as u8;
-        debug_print(self.opcode);
+    fn op_0x6xkk(&mut self, reg_x: usize, kk: u8) {
+        self.reg_v[reg_x] = kk;
     }
 
     // Set Vx = Vx + kk
-    fn op_0x7xkk(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_kk = (self.opcode & 0x00FF) as u8;
-        self.reg_v[reg_x] = self.reg_v[reg_x].wrapping_add(reg_kk);
-        debug_print(self.opcode);
+    fn op_0x7xkk(&mut self, reg_x: usize, kk: u8) {
+        self.reg_v[reg_x] = self.reg_v[reg_x].wrapping_add(kk);
     }
 
     // Set Vx = Vy
-    fn op_0x8xy0(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
+    fn op_0x8xy0(&mut self, reg_x: usize, reg_y: usize) {
         self.reg_v[reg_x] = self.reg_v[reg_y];
-        debug_print(self.opcode);
     }
 
     // Set Vx = Vx OR Vy
-    fn op_0x8xy1(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
-        self.reg_v[reg_x] = self.reg_v[reg_x] | self.reg_v[reg_y];
-        debug_print(self.opcode);
+    fn op_0x8xy1(&mut self, reg_x: usize, reg_y: usize) {
+        self.reg_v[reg_x] |= self.reg_v[reg_y];
+        self.logic_vf_reset();
     }
 
     // Set Vx = Vx AND Vy
-    fn op_0x8xy2(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
-        self.reg_v[reg_x] = self.reg_v[reg_x] & self.reg_v[reg_y];
-        debug_print(self.opcode);
+    fn op_0x8xy2(&mut self, reg_x: usize, reg_y: usize) {
+        self.reg_v[reg_x] &= self.reg_v[reg_y];
+        self.logic_vf_reset();
     }
 
     // Set Vx = Vx XOR Vy
-    fn op_0x8xy3(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
-        self.reg_v[reg_x] = self.reg_v[reg_x] ^ self.reg_v[reg_y];
-        debug_print(self.opcode);
+    fn op_0x8xy3(&mut self, reg_x: usize, reg_y: usize) {
+        self.reg_v[reg_x] ^= self.reg_v[reg_y];
+        self.logic_vf_reset();
     }
 
-    // Set Vx = Vx + Vy, set VF = carry
-    fn op_0x8xy4(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
+    // VF is reset by the logic opcodes on the COSMAC VIP
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.reg_v[0xF] = 0;
+        }
+    }
 
+    // Set Vx = Vx + Vy, set VF = carry
+    fn op_0x8xy4(&mut self, reg_x: usize, reg_y: usize) {
         let (result, carry) = self.reg_v[reg_x].overflowing_add(self.reg_v[reg_y]);
         self.reg_v[reg_x] = result;
         self.reg_v[0xF] = if carry { 1 } else { 0 };
-
-        debug_print(self.opcode);
     }
 
     // Set Vx = Vx - Vy, set VF = NOT borrow
-    fn op_0x8xy5(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
+    fn op_0x8xy5(&mut self, reg_x: usize, reg_y: usize) {
         let (result, borrow) = self.reg_v[reg_x].overflowing_sub(self.reg_v[reg_y]);
         self.reg_v[reg_x] = result;
         self.reg_v[0xF] = if borrow { 0 } else { 1 };
-        debug_print(self.opcode);
     }
 
     // Set Vx = Vx SHIFT RIGHT 1, set VF = least significant bit of Vx before shift
-    fn op_0x8xy6(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0x8xy6(&mut self, reg_x: usize, reg_y: usize) {
+        self.shift_source(reg_x, reg_y);
         self.reg_v[0xF] = self.reg_v[reg_x] & 0x1;
-        self.reg_v[reg_x] = self.reg_v[reg_x] >> 1;
-        debug_print(self.opcode);
+        self.reg_v[reg_x] >>= 1;
     }
 
     // Set Vx = Vy - Vx, set VF = NOT borrow
-    fn op_0x8xy7(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
+    fn op_0x8xy7(&mut self, reg_x: usize, reg_y: usize) {
         let (result, borrow) = self.reg_v[reg_y].overflowing_sub(self.reg_v[reg_x]);
         self.reg_v[reg_x] = result;
         self.reg_v[0xF] = if borrow { 0 } else { 1 };
-        debug_print(self.opcode);
     }
 
     // Set Vx = Vx SHIFT LEFT 1, set VF = most significant bit of Vx before shift
-    fn op_0x8xyE(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0x8xyE(&mut self, reg_x: usize, reg_y: usize) {
+        self.shift_source(reg_x, reg_y);
         self.reg_v[0xF] = (self.reg_v[reg_x] & 0x80) >> 7;
-        self.reg_v[reg_x] = self.reg_v[reg_x] << 1;
-        debug_print(self.opcode);
+        self.reg_v[reg_x] <<= 1;
+    }
+
+    // The original interpreter shifts Vy and stores the result in Vx
+    fn shift_source(&mut self, reg_x: usize, reg_y: usize) {
+        if self.quirks.shift_uses_vy {
+            self.reg_v[reg_x] = self.reg_v[reg_y];
+        }
     }
 
     // Skips the next instruction if VX does not equal VY
-    fn op_0x9xy0(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.opcode & 0x00F0) >> 4) as usize;
+    fn op_0x9xy0(&mut self, reg_x: usize, reg_y: usize) {
         if self.reg_v[reg_x] != self.reg_v[reg_y] {
-            self.reg_pc += 2;
+            self.skip();
         }
-        debug_print(self.opcode);
     }
 
     // Sets I to the address NNN.
-    fn op_0xAnnn(&mut self) {
-        self.reg_i = self.opcode & 0x0FFF;
-        debug_print(self.opcode);
+    fn op_0xAnnn(&mut self, nnn: u16) {
+        self.reg_i = nnn;
     }
 
-    // Jumps to address NNN plus V0.
-    fn op_0xBnnn(&mut self) {
-        self.reg_pc = (self.opcode & 0x0FFF) + self.reg_v[0] as u16;
-        debug_print(self.opcode);
+    // Jumps to address NNN plus V0 (or Vx on CHIP-48 and SCHIP).
+    fn op_0xBnnn(&mut self, nnn: u16) {
+        let reg = if self.quirks.jump_uses_vx {
+            (nnn >> 8) as usize
+        } else {
+            0
+        };
+        self.reg_pc = nnn + self.reg_v[reg] as u16;
     }
 
     // Sets Vx = random byte AND kk.
-    fn op_0xCxkk(&mut self) {
-        let mut rng = rand::thread_rng();
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        self.reg_v[reg_x] = rng.gen_range(0..0xFF) & (self.opcode & 0x00FF) as u8;
-        debug_print(self.opcode);
+    fn op_0xCxkk(&mut self, reg_x: usize, kk: u8) {
+        self.reg_v[reg_x] = self.rng.gen::<u8>() & kk;
     }
 
     // Draws a sprite at coordinate (Vx, Vy) with width 8 pixels and height N pixels.
-    fn op_0xDxyn(&mut self) {
-        let vx = ((self.opcode & 0x0F00) >> 8) as usize;
-        let vy = ((self.opcode & 0x00F0) >> 4) as usize;
-        let height = (self.opcode & 0x000F) as usize;
+    fn op_0xDxyn(&mut self, reg_x: usize, reg_y: usize, n: u8) -> Result<(), CpuError> {
+        let mut height = n as usize;
+
+        // SCHIP draws 16x16 sprites for N = 0
+        let mut width = 8;
+        if height == 0 && self.platform != Platform::Chip8 {
+            width = 16;
+            height = 16;
+        }
+        let bytes_per_line = width / 8;
 
         self.reg_v[0xF] = 0;
-
-        for i in 0..height {
-            let sprite_line = self.ram[self.reg_i as usize + i];
-            for j in 0..8 {
-                let pixel = (sprite_line >> (7 - j)) & 0x1;
-                let x = (self.reg_v[vx] as usize + j) % 64;
-                let y = (self.reg_v[vy] as usize + i) % 32;
-                if pixel == 1 && self.video_ram[y][x] == 1 {
-                    self.reg_v[0xF] = 1;
+        let x = self.reg_v[reg_x] as usize;
+        let y = self.reg_v[reg_y] as usize;
+
+        // Each selected plane draws its own sprite, stored one after the other
+        let mut address = self.reg_i as usize;
+        for plane in [1u8, 2] {
+            if self.planes & plane == 0 {
+                continue;
+            }
+            let mut rows = Vec::with_capacity(height);
+            for _ in 0..height {
+                let mut sprite_line = 0u16;
+                for _ in 0..bytes_per_line {
+                    sprite_line = sprite_line << 8 | self.read_ram(address)? as u16;
+                    address += 1;
                 }
-                self.video_ram[y][x] ^= pixel;
+                rows.push(sprite_line);
+            }
+            let clip = self.quirks.clip_sprites;
+            if self
+                .framebuffer
+                .draw_sprite(x, y, width, &rows, plane, clip)
+            {
+                self.reg_v[0xF] = 1;
             }
         }
-        //TODO: implement this
-        debug_print(self.opcode);
-        debug_print_video_ram(&self.video_ram);
+        self.vblank_wait = self.quirks.display_wait;
+        debug_print_framebuffer(&self.framebuffer);
+        Ok(())
     }
 
     // Skips the next instruction if the key stored in VX is pressed.
-    fn op_0xEx9E(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        if self.keyboard[self.reg_v[reg_x] as usize] == true {
-            self.reg_pc += 2;
+    fn op_0xEx9E(&mut self, reg_x: usize) {
+        if self.keyboard[(self.reg_v[reg_x] & 0xF) as usize] {
+            self.skip();
         }
-        debug_print(self.opcode);
     }
 
     // Skips the next instruction if the key stored in VX is not pressed.
-    fn op_0xExA1(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        if self.keyboard[self.reg_v[reg_x] as usize] == false {
-            self.reg_pc += 2;
+    fn op_0xExA1(&mut self, reg_x: usize) {
+        if !self.keyboard[(self.reg_v[reg_x] & 0xF) as usize] {
+            self.skip();
         }
-        debug_print(self.opcode);
     }
 
     // Sets Vx = delay timer value.
-    fn op_0xFx07(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx07(&mut self, reg_x: usize) {
         self.reg_v[reg_x] = self.reg_delay_timer;
-        debug_print(self.opcode);
     }
 
     // Awaits a key press, then stores the value of the key in VX.
-    fn op_0xFx0A(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx0A(&mut self, reg_x: usize) {
         let mut key_pressed = false;
-        for i in 0..16 {
-            if self.keyboard[i] == true {
+        for (i, pressed) in self.keyboard.iter().enumerate() {
+            if *pressed {
                 self.reg_v[reg_x] = i as u8;
                 key_pressed = true;
             }
@@ -422,76 +711,120 @@ impl Cpu {
         if !key_pressed {
             self.reg_pc -= 2;
         }
-        debug_print(self.opcode);
     }
 
     // Sets the delay timer = Vx.
-    fn op_0xFx15(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx15(&mut self, reg_x: usize) {
         self.reg_delay_timer = self.reg_v[reg_x];
-        debug_print(self.opcode);
     }
 
     // Sets the sound timer = Vx.
-    fn op_0xFx18(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx18(&mut self, reg_x: usize) {
         self.reg_sound_timer = self.reg_v[reg_x];
-        debug_print(self.opcode);
     }
 
     // Adds Vx to I.
-    fn op_0xFx1E(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        self.reg_i += self.reg_v[reg_x] as u16;
-        debug_print(self.opcode);
+    fn op_0xFx1E(&mut self, reg_x: usize) {
+        self.reg_i = self.reg_i.wrapping_add(self.reg_v[reg_x] as u16);
     }
 
     // Sets I = location of sprite for digit Vx.
-    fn op_0xFx29(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        self.reg_i = self.reg_v[reg_x] as u16 * 5;
-        debug_print(self.opcode);
+    fn op_0xFx29(&mut self, reg_x: usize) {
+        self.reg_i = (self.layout.font_address + (self.reg_v[reg_x] & 0xF) as usize * 5) as u16;
+    }
+
+    // Sets I = the 16 bit address NNNN following the opcode.
+    fn op_0xF000(&mut self) -> Result<(), CpuError> {
+        if self.reg_pc as usize + 1 >= self.ram.len() {
+            return Err(CpuError::MemoryFault {
+                address: self.reg_pc as usize,
+                pc: self.opcode_address(),
+            });
+        }
+        self.reg_i = self.read_word(self.reg_pc as usize);
+        self.reg_pc = self.reg_pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // Selects the drawing planes N.
+    fn op_0xFn01(&mut self, n: u8) {
+        self.planes = n & 0x3;
+    }
+
+    // Loads the 16 byte audio pattern from memory starting at location I.
+    fn op_0xF002(&mut self) -> Result<(), CpuError> {
+        for i in 0..self.audio_pattern.len() {
+            self.audio_pattern[i] = self.read_ram(self.reg_i as usize + i)?;
+        }
+        Ok(())
+    }
+
+    // Sets the audio pitch = Vx.
+    fn op_0xFx3A(&mut self, reg_x: usize) {
+        self.audio_pitch = self.reg_v[reg_x];
+    }
+
+    // Sets I = location of the big sprite for digit Vx.
+    fn op_0xFx30(&mut self, reg_x: usize) {
+        let digit = (self.reg_v[reg_x] & 0xF) as usize;
+        self.reg_i = (self.layout.big_font_address + digit * 10) as u16;
+    }
+
+    // Stores registers V0 to Vx in the RPL user flags.
+    fn op_0xFx75(&mut self, reg_x: usize) {
+        self.rpl_flags[..reg_x + 1].copy_from_slice(&self.reg_v[..reg_x + 1]);
+    }
+
+    // Fills registers V0 to Vx from the RPL user flags.
+    fn op_0xFx85(&mut self, reg_x: usize) {
+        self.reg_v[..reg_x + 1].copy_from_slice(&self.rpl_flags[..reg_x + 1]);
     }
 
     // Stores BCD representation of Vx in memory locations I, I+1, and I+2.
-    fn op_0xFx33(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
-        self.ram[self.reg_i as usize] = self.reg_v[reg_x] / 100;
-        self.ram[self.reg_i as usize + 1] = (self.reg_v[reg_x] % 100) / 10;
-        self.ram[self.reg_i as usize + 2] = self.reg_v[reg_x] % 10;
-        debug_print(self.opcode);
+    fn op_0xFx33(&mut self, reg_x: usize) -> Result<(), CpuError> {
+        let address = self.reg_i as usize;
+        self.write_ram(address, self.reg_v[reg_x] / 100)?;
+        self.write_ram(address + 1, (self.reg_v[reg_x] % 100) / 10)?;
+        self.write_ram(address + 2, self.reg_v[reg_x] % 10)?;
+        Ok(())
+    }
+
+    // Moves I past the registers accessed by Fx55/Fx65 as far as the quirks ask for
+    fn load_store_increment_i(&mut self, reg_x: usize) {
+        if self.quirks.load_store_increments_i {
+            let count = if self.quirks.load_store_increments_i_by_x {
+                reg_x
+            } else {
+                reg_x + 1
+            };
+            self.reg_i = self.reg_i.wrapping_add(count as u16);
+        }
     }
 
     // Stores registers V0 to Vx in memory starting at location I.
-    fn op_0xFx55(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx55(&mut self, reg_x: usize) -> Result<(), CpuError> {
         for i in 0..reg_x + 1 {
-            self.ram[self.reg_i as usize + i] = self.reg_v[i];
+            self.write_ram(self.reg_i as usize + i, self.reg_v[i])?;
         }
-        debug_print(self.opcode);
+        self.load_store_increment_i(reg_x);
+        Ok(())
     }
 
     // Fills registers V0 to Vx with values from memory starting at location I.
-    fn op_0xFx65(&mut self) {
-        let reg_x = ((self.opcode & 0x0F00) >> 8) as usize;
+    fn op_0xFx65(&mut self, reg_x: usize) -> Result<(), CpuError> {
         for i in 0..reg_x + 1 {
-            self.reg_v[i] = self.ram[self.reg_i as usize + i];
+            self.reg_v[i] = self.read_ram(self.reg_i as usize + i)?;
         }
-        debug_print(self.opcode);
-    }
-}
-
-fn debug_print(opcode: u16) {
-    if CPU_DEBUG_PRINT {
-        println!("Opcode: {:04x}", opcode);
+        self.load_store_increment_i(reg_x);
+        Ok(())
     }
 }
 
-fn debug_print_video_ram(video_ram: &[[u8; 64]; 32]) {
-    if CPU_DEBUG_PRINT_VIDEO_RAM {
-        for i in 0..32 {
-            for j in 0..64 {
-                print!("{}", video_ram[i][j]);
+fn debug_print_framebuffer(framebuffer: &Framebuffer) {
+    if CPU_DEBUG_PRINT_FRAMEBUFFER {
+        for row in framebuffer.rows() {
+            for pixel in row.iter() {
+                print!("{}", pixel);
             }
             println!();
         }
@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::{Instruction, MemoryLayout, Platform};
+
+#[cfg(test)]
+#[path = "./tests/disasm.rs"]
+mod tests;
+
+// Data bytes per db line
+const DATA_BYTES_PER_LINE: usize = 8;
+
+// Listing of a ROM that separates reachable code from data.
+// Code is found by following the control flow from the program start.
+pub fn disassemble(rom: &[u8], platform: Platform) -> String {
+    disassemble_with_layout(rom, platform, MemoryLayout::for_platform(platform))
+}
+
+// Listing of a ROM loaded at the program start of the layout
+pub fn disassemble_with_layout(rom: &[u8], platform: Platform, layout: MemoryLayout) -> String {
+    let start = layout.program_start;
+    let code = trace_code(rom, platform, start);
+    let labels = collect_labels(rom, &code, start);
+
+    let mut listing = String::new();
+    let end = start + rom.len();
+    let mut address = start;
+    while address < end {
+        if let Some(label) = labels.get(&address) {
+            writeln!(listing, "{}:", label).unwrap();
+        }
+
+        if let Some(instruction) = code.get(&address) {
+            let size = instruction.size() as usize;
+            let text = format_instruction(instruction, rom, address, start, &labels);
+            let bytes = hex_bytes(&rom[address - start..address - start + size]);
+            writeln!(listing, "    {:<23} ; {:03X}: {}", text, address, bytes).unwrap();
+            address += size;
+        } else {
+            // Data runs until the next code or label, split into short lines
+            let data_start = address;
+            address += 1;
+            while address < end
+                && address - data_start < DATA_BYTES_PER_LINE
+                && !code.contains_key(&address)
+                && !labels.contains_key(&address)
+            {
+                address += 1;
+            }
+            let bytes = &rom[data_start - start..address - start];
+            let values: Vec<String> = bytes.iter().map(|b| format!("0x{:02X}", b)).collect();
+            let text = format!("db {}", values.join(", "));
+            writeln!(listing, "    {:<23} ; {:03X}", text, data_start).unwrap();
+        }
+    }
+    listing
+}
+
+// Follow jumps, calls and skips from the program start.
+// Returns the instructions found, keyed by their address.
+fn trace_code(rom: &[u8], platform: Platform, start: usize) -> BTreeMap<usize, Instruction> {
+    let mut code = BTreeMap::new();
+    let mut covered = BTreeSet::new();
+    let mut pending = vec![start];
+
+    while let Some(address) = pending.pop() {
+        if code.contains_key(&address) {
+            continue;
+        }
+        let instruction = match decode_at(rom, address, platform, start) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        let size = instruction.size() as usize;
+        let next = address + size;
+        // Don't decode instructions that overlap code already found
+        if (address..next).any(|a| covered.contains(&a)) {
+            continue;
+        }
+        covered.extend(address..next);
+        code.insert(address, instruction);
+
+        match instruction {
+            Instruction::Jump { nnn } | Instruction::JumpOffset { nnn } => {
+                // Bnnn jumps somewhere behind nnn, usually into a jump table at nnn
+                pending.push(nnn as usize);
+            }
+            Instruction::Call { nnn } => {
+                pending.push(nnn as usize);
+                pending.push(next);
+            }
+            Instruction::Ret | Instruction::Exit => {}
+            Instruction::SkipEqImm { .. }
+            | Instruction::SkipNeImm { .. }
+            | Instruction::SkipEqReg { .. }
+            | Instruction::SkipNeReg { .. }
+            | Instruction::SkipKey { .. }
+            | Instruction::SkipNotKey { .. } => {
+                pending.push(next);
+                // The skipped instruction may be the 4 byte F000 NNNN
+                let skipped =
+                    decode_at(rom, next, platform, start).map_or(2, |i| i.size() as usize);
+                pending.push(next + skipped);
+            }
+            _ => pending.push(next),
+        }
+    }
+    code
+}
+
+// Decode the instruction at a memory address, if the ROM holds a valid one there
+fn decode_at(rom: &[u8], address: usize, platform: Platform, start: usize) -> Option<Instruction> {
+    let offset = address.checked_sub(start)?;
+    let word = read_word(rom, offset)?;
+    let instruction = Instruction::decode_for(word, platform).ok()?;
+    if offset + instruction.size() as usize > rom.len() {
+        return None;
+    }
+    Some(instruction)
+}
+
+fn read_word(rom: &[u8], offset: usize) -> Option<u16> {
+    let high = *rom.get(offset)?;
+    let low = *rom.get(offset + 1)?;
+    Some((high as u16) << 8 | low as u16)
+}
+
+// Name every address inside the ROM that the code refers to.
+// Only addresses that start a line of the listing can be labelled.
+fn collect_labels(
+    rom: &[u8],
+    code: &BTreeMap<usize, Instruction>,
+    start: usize,
+) -> BTreeMap<usize, String> {
+    let end = start + rom.len();
+    let inside_instruction = |target: usize| {
+        code.range(..target)
+            .next_back()
+            .is_some_and(|(address, i)| target < address + i.size() as usize)
+    };
+
+    let mut labels = BTreeMap::new();
+    for (&address, instruction) in code {
+        let (target, prefix) = match *instruction {
+            Instruction::Jump { nnn }
+            | Instruction::Call { nnn }
+            | Instruction::JumpOffset { nnn } => (nnn as usize, "L"),
+            Instruction::LoadI { nnn } => (nnn as usize, "D"),
+            Instruction::LoadILong => match read_word(rom, address - start + 2) {
+                Some(nnnn) => (nnnn as usize, "D"),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if target < start || target >= end || inside_instruction(target) {
+            continue;
+        }
+        // Code labels win over data labels
+        let prefix = if code.contains_key(&target) {
+            "L"
+        } else {
+            prefix
+        };
+        labels.insert(target, format!("{}{:03X}", prefix, target));
+    }
+    labels
+}
+
+// Mnemonic with addresses replaced by their labels
+fn format_instruction(
+    instruction: &Instruction,
+    rom: &[u8],
+    address: usize,
+    start: usize,
+    labels: &BTreeMap<usize, String>,
+) -> String {
+    let target = |nnn: u16| match labels.get(&(nnn as usize)) {
+        Some(label) => label.clone(),
+        None => format!("0x{:03X}", nnn),
+    };
+    match *instruction {
+        Instruction::Jump { nnn } => format!("JP {}", target(nnn)),
+        Instruction::Call { nnn } => format!("CALL {}", target(nnn)),
+        Instruction::JumpOffset { nnn } => format!("JP V0, {}", target(nnn)),
+        Instruction::LoadI { nnn } => format!("LD I, {}", target(nnn)),
+        Instruction::LoadILong => {
+            let nnnn = read_word(rom, address - start + 2).unwrap_or(0);
+            match labels.get(&(nnnn as usize)) {
+                Some(label) => format!("LD I, LONG {}", label),
+                None => format!("LD I, LONG 0x{:04X}", nnnn),
+            }
+        }
+        _ => instruction.to_string(),
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let values: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    values.join(" ")
+}
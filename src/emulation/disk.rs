@@ -1,27 +1,152 @@
-use std::{fs::File, io::Read};
+use std::{fmt, fs, fs::File, io, io::Read, path::Path};
+
+use super::{hex, MemoryLayout, Platform, RomInfo};
+
+#[cfg(test)]
+#[path = "./tests/disk.rs"]
+mod tests;
+
+#[derive(Debug)]
+pub enum DiskError {
+    // No file at the path
+    NotFound { path: String },
+    // The file has no bytes to load
+    Empty { path: String },
+    // The program doesn't fit between the program start and the end of memory
+    TooLarge { size: usize, max: usize },
+    // Fewer bytes were read than the file has
+    PartialRead { read: usize, expected: usize },
+    // A hex text file could not be parsed
+    Parse { line: usize, message: String },
+    // Any other failure to read the file
+    Io(io::Error),
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskError::NotFound { path } => write!(f, "ROM not found: {}", path),
+            DiskError::Empty { path } => write!(f, "ROM is empty: {}", path),
+            DiskError::TooLarge { size, max } => {
+                write!(f, "ROM too large: {} bytes, at most {} fit", size, max)
+            }
+            DiskError::PartialRead { read, expected } => {
+                write!(f, "ROM only partially read: {} of {} bytes", read, expected)
+            }
+            DiskError::Parse { line, message } => {
+                write!(f, "ROM hex invalid in line {}: {}", line, message)
+            }
+            DiskError::Io(err) => write!(f, "ROM could not be read: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DiskError {}
+
+impl From<io::Error> for DiskError {
+    fn from(err: io::Error) -> Self {
+        DiskError::Io(err)
+    }
+}
+
 pub struct Disk {
-    pub rom: [u8; 4095],
+    pub rom: Vec<u8>,
     pub size: usize,
+    // Database entry of a known ROM
+    pub info: Option<&'static RomInfo>,
+    // Contents of the .txt file next to the ROM, if there is one
+    pub description: Option<String>,
 }
 
 impl Disk {
+    // Read a ROM file into the 64 KiB of XO-CHIP, panics where load returns an error
+    #[deprecated(note = "use Disk::load, which returns an error instead of panicking")]
     pub fn new(file_path: &str) -> Disk {
-        let mut rom = [0; 4095];
-        let mut file = File::open(file_path).unwrap();
-        let size = file.read(&mut rom).unwrap();
+        Disk::load(file_path, Platform::XoChip).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Read a whole ROM file, refusing it if it doesn't fit into the platform's memory.
+    // .hex files hold plain hex or Intel HEX text instead of binary.
+    pub fn load(file_path: &str, platform: Platform) -> Result<Disk, DiskError> {
+        Disk::load_with_layout(file_path, MemoryLayout::for_platform(platform))
+    }
+
+    // Read a whole ROM file for the program start and memory of the layout
+    pub fn load_with_layout(file_path: &str, layout: MemoryLayout) -> Result<Disk, DiskError> {
+        let mut file = File::open(file_path).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => DiskError::NotFound {
+                path: file_path.to_string(),
+            },
+            _ => DiskError::Io(err),
+        })?;
+        let expected = file.metadata()?.len() as usize;
+
+        let mut rom = Vec::with_capacity(expected);
+        let read = file.read_to_end(&mut rom)?;
+        if read < expected {
+            return Err(DiskError::PartialRead { read, expected });
+        }
+
+        let path = Path::new(file_path);
+        let is_hex = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("hex"));
+        if is_hex {
+            let text = String::from_utf8_lossy(&rom);
+            rom = if hex::is_intel(&text) {
+                hex::from_intel(&text, layout.program_start, layout.max_program_size())?
+            } else {
+                hex::from_plain(&text)?
+            };
+        }
+
+        let size = rom.len();
+        if size == 0 {
+            return Err(DiskError::Empty {
+                path: file_path.to_string(),
+            });
+        }
+        let max = layout.max_program_size();
+        if size > max {
+            return Err(DiskError::TooLarge { size, max });
+        }
+
+        let mut disk = Disk::from_rom(rom);
+        disk.description = fs::read_to_string(path.with_extension("txt")).ok();
+        Ok(disk)
+    }
+
+    // Disk for ROM bytes that don't come from a file
+    pub fn from_rom(rom: Vec<u8>) -> Disk {
         Disk {
-            rom: rom,
-            size: size,
+            size: rom.len(),
+            info: RomInfo::lookup(&rom),
+            rom,
+            description: None,
         }
     }
 
+    // Plain hex text, 16 bytes per line, as printed by print_disk
+    pub fn to_hex(&self) -> String {
+        hex::to_plain(&self.rom)
+    }
+
+    // Read plain hex text back, whitespace and comments are ignored
+    pub fn from_hex(text: &str) -> Result<Disk, DiskError> {
+        hex::from_plain(text).map(Disk::from_rom)
+    }
+
+    // Intel HEX with the program at the program start of the layout
+    pub fn to_intel_hex(&self, layout: MemoryLayout) -> String {
+        hex::to_intel(&self.rom, layout.program_start)
+    }
+
+    // Intel HEX for a program that fits between the program start and the end of memory
+    pub fn from_intel_hex(text: &str, layout: MemoryLayout) -> Result<Disk, DiskError> {
+        hex::from_intel(text, layout.program_start, layout.max_program_size()).map(Disk::from_rom)
+    }
+
     pub fn print_disk(&self) {
-        for i in 0..self.size {
-            print!("{:02x} ", self.rom[i as usize]);
-            if i % 16 == 15 {
-                println!("");
-            }
-        }
-        println!("");
+        print!("{}", self.to_hex());
     }
 }
@@ -1,54 +1,208 @@
 use piston_window::{
-    clear, rectangle, types::Color, Context, G2d, Graphics, PistonWindow, WindowSettings,
+    clear, image, rectangle,
+    texture::{CreateTexture, Format, UpdateTexture},
+    types::Color,
+    Filter, G2dTexture, G2dTextureContext, ImageSize, PistonWindow, RenderEvent, TextureSettings,
+    Transformed, WindowSettings,
 };
 
-use crate::{BACKCOLOR, FRONTCOLOR};
+use glutin::window::Fullscreen;
 
-use super::Cpu;
+use super::{text, viewport, FrameBlender, Palette, RomBrowser, Scaling};
+
+// Bars around a picture that doesn't fill the window
+const LETTERBOX: Color = [0.0, 0.0, 0.0, 1.0];
+
+// Screen pixels per font pixel of the ROM browser
+const TEXT_SCALE: u32 = 4;
+// ROMs listed at once, the list scrolls along with the selection
+const BROWSER_LIST_ROWS: usize = 8;
+
+// 0xRRGGBB as a piston colour
+fn colour(rgb: u32) -> Color {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+}
 
 pub struct Display {
-    width: u32,
-    height: u32,
-    pub scale: u32,
+    // Colour as 0xRRGGBB for each combination of the two XO-CHIP planes
+    palette: [u32; 4],
     pub window: PistonWindow,
+    // The picture as a single texture, uploaded again only when it changed
+    texture_context: G2dTextureContext,
+    texture: Option<G2dTexture>,
+    // The texture has to be uploaded even if the framebuffer didn't change,
+    // e.g. after a palette change
+    texture_stale: bool,
+    pub scaling: Scaling,
+    fullscreen: bool,
 }
 
 impl Display {
     pub fn new(chip8_width: u32, chip8_height: u32, chip8_scale: u32, title: &str) -> Display {
-        let mut window = WindowSettings::new(
+        let mut window: PistonWindow = WindowSettings::new(
             title,
             [chip8_width * chip8_scale, chip8_height * chip8_scale],
         )
         .exit_on_esc(true)
+        .resizable(true)
         .build()
         .unwrap();
 
         Display {
-            width: chip8_width * chip8_scale,
-            height: chip8_height * chip8_scale,
-            scale: chip8_scale,
-            window: window,
+            palette: Palette::default().colours,
+            texture_context: window.create_texture_context(),
+            window,
+            texture: None,
+            texture_stale: true,
+            scaling: Scaling::Integer,
+            fullscreen: false,
         }
     }
 
-    pub fn draw(&mut self, cpu: &Cpu, e: &piston_window::Event) {
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    // Borderless fullscreen on the current monitor, or back to the window
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        let mode = fullscreen.then_some(Fullscreen::Borderless(None));
+        self.window.window.ctx.window().set_fullscreen(mode);
+        self.fullscreen = fullscreen;
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.set_fullscreen(!self.fullscreen);
+    }
+
+    pub fn palette(&self) -> [u32; 4] {
+        self.palette
+    }
+
+    // Use colours given as 0xRRGGBB, e.g. from a theme or the ROM database
+    pub fn set_colours(&mut self, colours: [u32; 4]) {
+        self.palette = colours;
+        self.texture_stale = true;
+    }
+
+    // Draw the frames blended against flicker on render events
+    pub fn draw(&mut self, blender: &mut FrameBlender, e: &piston_window::Event) {
+        let args = match e.render_args() {
+            Some(args) if blender.width() > 0 => args,
+            _ => return,
+        };
+        if blender.take_changed() || self.texture_stale {
+            self.upload(blender);
+        }
+        let texture = match &self.texture {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        // The window size is looked at every frame, so resizing the window or switching
+        // the resolution changes the scale. The texture is sampled nearest neighbour.
+        let picture_size = [blender.width(), blender.height()];
+        let [left, top, width, height] = viewport(args.window_size, picture_size, self.scaling);
+        let scale_x = width / picture_size[0] as f64;
+        let scale_y = height / picture_size[1] as f64;
+
+        let texture_context = &mut self.texture_context;
+        self.window.draw_2d(e, |c, g, device| {
+            texture_context.encoder.flush(device);
+            clear(LETTERBOX, g);
+            let transform = c.transform.trans(left, top).scale(scale_x, scale_y);
+            image(texture, transform, g);
+        });
+    }
+
+    // Convert the picture to RGBA and upload it, the texture is
+    // created again when the resolution changed
+    fn upload(&mut self, blender: &FrameBlender) {
+        let rgba = blender.to_rgba(&self.palette);
+        let size = [blender.width() as u32, blender.height() as u32];
+
+        let texture_context = &mut self.texture_context;
+        let result = match &mut self.texture {
+            Some(texture) if texture.get_size() == (size[0], size[1]) => {
+                UpdateTexture::update(texture, texture_context, Format::Rgba8, &rgba, [0, 0], size)
+            }
+            _ => {
+                let settings = TextureSettings::new().filter(Filter::Nearest);
+                G2dTexture::create(texture_context, Format::Rgba8, &rgba, size, &settings)
+                    .map(|texture| self.texture = Some(texture))
+            }
+        };
+        match result {
+            Ok(()) => self.texture_stale = false,
+            Err(error) => println!("Picture texture could not be uploaded: {:?}", error),
+        }
+    }
+
+    // List of ROMs with the selected one highlighted, followed by its description
+    pub fn draw_browser(&mut self, browser: &RomBrowser, e: &piston_window::Event) {
+        let [window_width, window_height] = match e.render_args() {
+            Some(args) => args.window_size,
+            None => return,
+        };
+        let cell_width = (text::GLYPH_WIDTH as u32 + 1) * TEXT_SCALE;
+        let cell_height = (text::GLYPH_HEIGHT as u32 + 1) * TEXT_SCALE;
+        let columns = (window_width as u32 / cell_width) as usize;
+        let rows = (window_height as u32 / cell_height) as usize;
+
+        let mut lines = vec![(format!("ROMS IN {}", browser.dir.display()), 3)];
+        lines.push((String::new(), 1));
+        if browser.entries.is_empty() {
+            lines.push(("NO ROMS FOUND".to_string(), 1));
+        }
+        let first = browser
+            .selected
+            .saturating_sub(BROWSER_LIST_ROWS - 1)
+            .min(browser.entries.len().saturating_sub(BROWSER_LIST_ROWS));
+        for (index, entry) in browser
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(BROWSER_LIST_ROWS)
+        {
+            if index == browser.selected {
+                lines.push((format!("> {}", entry.name), 2));
+            } else {
+                lines.push((format!("  {}", entry.name), 1));
+            }
+        }
+        lines.push((String::new(), 1));
+        if let Some(description) = browser.selected().and_then(|e| e.description.as_ref()) {
+            for line in description.lines() {
+                let chars: Vec<char> = line.chars().collect();
+                for chunk in chars.chunks(columns.max(1)) {
+                    lines.push((chunk.iter().collect(), 1));
+                }
+            }
+        }
+        // The message always stays visible in the last row
+        lines.truncate(rows.saturating_sub(1));
+        if let Some(message) = &browser.message {
+            lines.resize(rows.saturating_sub(1), (String::new(), 1));
+            lines.push((message.clone(), 3));
+        }
+
         self.window.draw_2d(e, |c, g, _| {
-            clear(BACKCOLOR, g);
-            for y in 0..cpu.video_ram.len() {
-                for x in 0..cpu.video_ram[y].len() {
-                    if cpu.video_ram[y][x] == 1 {
-                        rectangle(
-                            FRONTCOLOR,
-                            [
-                                x as f64 * self.scale as f64,
-                                y as f64 * self.scale as f64,
-                                self.scale as f64,
-                                self.scale as f64,
-                            ],
-                            c.transform,
-                            g,
-                        );
-                    }
+            clear(colour(self.palette[0]), g);
+            for (row, (line, index)) in lines.iter().enumerate() {
+                let line: String = line.chars().take(columns).collect();
+                for (x, y) in text::pixels(&line) {
+                    rectangle(
+                        colour(self.palette[*index]),
+                        [
+                            (x as u32 * TEXT_SCALE) as f64,
+                            (row as u32 * cell_height + y as u32 * TEXT_SCALE) as f64,
+                            TEXT_SCALE as f64,
+                            TEXT_SCALE as f64,
+                        ],
+                        c.transform,
+                        g,
+                    );
                 }
             }
         });
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use super::Framebuffer;
+
+#[cfg(test)]
+#[path = "./tests/flicker.rs"]
+mod tests;
+
+// CHIP-8 programs move sprites by erasing and drawing them again,
+// which flickers when a frame is shown in between
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiFlicker {
+    // Show every frame as it is
+    #[default]
+    Off,
+    // Show the pixels lit in this or the last frame
+    Or,
+    // Pixels fade out over the given number of frames like on a phosphor screen
+    Blend {
+        frames: u8,
+    },
+}
+
+impl AntiFlicker {
+    // Look up a mode by name, e.g. from the settings, blending fades out over 3 frames
+    pub fn from_name(name: &str) -> Option<AntiFlicker> {
+        match name.to_lowercase().as_str() {
+            "off" | "none" => Some(AntiFlicker::Off),
+            "or" => Some(AntiFlicker::Or),
+            "blend" => Some(AntiFlicker::Blend { frames: 3 }),
+            _ => None,
+        }
+    }
+
+    // Frames that make up the picture
+    fn history_length(&self) -> usize {
+        match self {
+            AntiFlicker::Off => 1,
+            AntiFlicker::Or => 2,
+            AntiFlicker::Blend { frames } => (*frames).max(1) as usize,
+        }
+    }
+}
+
+// Sits between the framebuffer and the renderer, keeping the last frames
+// to build the picture from
+pub struct FrameBlender {
+    pub mode: AntiFlicker,
+    width: usize,
+    height: usize,
+    // Pixels of the last frames, the newest first
+    history: VecDeque<Vec<u8>>,
+    changed: bool,
+}
+
+impl FrameBlender {
+    pub fn new(mode: AntiFlicker) -> Self {
+        FrameBlender {
+            mode,
+            width: 0,
+            height: 0,
+            history: VecDeque::new(),
+            changed: true,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Add the framebuffer at the end of a frame, taking its dirty flag
+    pub fn push(&mut self, framebuffer: &mut Framebuffer) {
+        let dirty = framebuffer.take_dirty();
+        let pixels = framebuffer.pixels();
+
+        // Old frames of another resolution can't be blended
+        if (framebuffer.width(), framebuffer.height()) != (self.width, self.height) {
+            self.width = framebuffer.width();
+            self.height = framebuffer.height();
+            self.history.clear();
+        }
+        // While older frames differ the picture keeps changing, even with a clean framebuffer
+        if dirty || self.history.iter().any(|frame| frame.as_slice() != pixels) {
+            self.changed = true;
+        }
+
+        self.history.push_front(pixels.to_vec());
+        self.history.truncate(self.mode.history_length());
+    }
+
+    // Called by the renderer, returns whether the picture has to be drawn again
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+
+    // RGBA bytes of the picture with colours from a 0xRRGGBB palette like Framebuffer::to_rgba
+    pub fn to_rgba(&self, palette: &[u32; 4]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        let frames = self.history.len();
+        for index in 0..self.width * self.height {
+            let pixel = |age: usize| self.history[age][index] & 0x3;
+            let colour = match self.mode {
+                AntiFlicker::Off => palette[pixel(0) as usize],
+                AntiFlicker::Or => {
+                    let planes = (0..frames).fold(0, |planes, age| planes | pixel(age));
+                    palette[planes as usize]
+                }
+                // The newest frame the pixel was lit in tells how far it faded
+                AntiFlicker::Blend { .. } => match (0..frames).find(|age| pixel(*age) != 0) {
+                    Some(age) => {
+                        let length = self.mode.history_length();
+                        let intensity = (length - age) as f32 / length as f32;
+                        mix(palette[0], palette[pixel(age) as usize], intensity)
+                    }
+                    None => palette[0],
+                },
+            };
+            let [_, r, g, b] = colour.to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+        rgba
+    }
+}
+
+// Colour between two 0xRRGGBB colours, amount 0 is from and 1 is to
+fn mix(from: u32, to: u32, amount: f32) -> u32 {
+    let [_, r0, g0, b0] = from.to_be_bytes();
+    let [_, r1, g1, b1] = to.to_be_bytes();
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u32;
+    channel(r0, r1) << 16 | channel(g0, g1) << 8 | channel(b0, b1)
+}
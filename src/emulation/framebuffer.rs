@@ -0,0 +1,185 @@
+#[cfg(test)]
+#[path = "./tests/framebuffer.rs"]
+mod tests;
+
+// Screen sizes of the CHIP-8 variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    // 64x32, CHIP-8 and the SCHIP low resolution
+    Lores,
+    // 64x64, two page CHIP-8 for the COSMAC VIP
+    Tall,
+    // 128x64, SCHIP and XO-CHIP high resolution
+    Hires,
+}
+
+impl Resolution {
+    pub fn width(&self) -> usize {
+        match self {
+            Resolution::Lores | Resolution::Tall => 64,
+            Resolution::Hires => 128,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            Resolution::Lores => 32,
+            Resolution::Tall | Resolution::Hires => 64,
+        }
+    }
+}
+
+// Screen contents with up to 8 bitplanes, each pixel holds one bit per plane.
+// Everything that changes pixels marks the framebuffer dirty until a renderer takes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    resolution: Resolution,
+    // Row by row, width times height
+    pixels: Vec<u8>,
+    dirty: bool,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new(Resolution::Lores)
+    }
+}
+
+impl Framebuffer {
+    pub fn new(resolution: Resolution) -> Self {
+        Framebuffer {
+            resolution,
+            pixels: vec![0; resolution.width() * resolution.height()],
+            dirty: true,
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    // Changing the resolution clears all planes
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        *self = Framebuffer::new(resolution);
+    }
+
+    // Plane bits of a pixel
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width() + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: u8) {
+        let width = self.width();
+        self.pixels[y * width + x] = value;
+        self.dirty = true;
+    }
+
+    // All pixels row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks(self.width())
+    }
+
+    // Clear the planes of the mask
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+        self.dirty = true;
+    }
+
+    // Move the planes of the mask by dx, dy pixels, shifting in blank pixels
+    pub fn scroll(&mut self, dx: isize, dy: isize, planes: u8) {
+        let width = self.width() as isize;
+        let height = self.height() as isize;
+        let source = self.pixels.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x - dx, y - dy);
+                let moved = if from_x >= 0 && from_x < width && from_y >= 0 && from_y < height {
+                    source[(from_y * width + from_x) as usize] & planes
+                } else {
+                    0
+                };
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                *pixel = (*pixel & !planes) | moved;
+            }
+        }
+        self.dirty = true;
+    }
+
+    // XOR a sprite into one plane, rows hold sprite_width pixels with the leftmost in the
+    // highest bit. The start position wraps, the sprite itself wraps or gets clipped at
+    // the edges. Returns whether a set pixel was erased.
+    pub fn draw_sprite(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite_width: usize,
+        rows: &[u16],
+        plane: u8,
+        clip: bool,
+    ) -> bool {
+        let width = self.width();
+        let height = self.height();
+        let start_x = x % width;
+        let start_y = y % height;
+
+        let mut collision = false;
+        for (i, row) in rows.iter().enumerate() {
+            for j in 0..sprite_width {
+                if (row >> (sprite_width - 1 - j)) & 0x1 == 0 {
+                    continue;
+                }
+                if clip && (start_x + j >= width || start_y + i >= height) {
+                    continue;
+                }
+                let index = (start_y + i) % height * width + (start_x + j) % width;
+                if self.pixels[index] & plane != 0 {
+                    collision = true;
+                }
+                self.pixels[index] ^= plane;
+            }
+        }
+        self.dirty = true;
+        collision
+    }
+
+    // RGBA bytes row by row, the palette has a 0xRRGGBB colour for each
+    // combination of the first two planes
+    pub fn to_rgba(&self, palette: &[u32; 4]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in self.pixels.iter() {
+            let [_, r, g, b] = palette[(pixel & 0x3) as usize].to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+        rgba
+    }
+
+    // Whether pixels changed since the renderer took the last frame
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    // Called by the renderer, returns whether the frame has to be drawn again
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
+    }
+
+    // Draw the next frame even if nothing changed, e.g. after the palette changed
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+}
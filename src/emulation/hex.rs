@@ -0,0 +1,181 @@
+use std::fmt::Write;
+
+use super::DiskError;
+
+#[cfg(test)]
+#[path = "./tests/hex.rs"]
+mod tests;
+
+// Bytes per line of a plain hex dump and per Intel HEX data record
+const BYTES_PER_LINE: usize = 16;
+
+// Intel HEX record types
+const RECORD_DATA: u8 = 0x00;
+const RECORD_END_OF_FILE: u8 = 0x01;
+const RECORD_EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const RECORD_START_SEGMENT_ADDRESS: u8 = 0x03;
+const RECORD_EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const RECORD_START_LINEAR_ADDRESS: u8 = 0x05;
+
+fn parse_error(line: usize, message: impl Into<String>) -> DiskError {
+    DiskError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+// Text without the comment starting with ';', '#' or "//"
+fn strip_comment(line: &str) -> &str {
+    let end = [line.find(';'), line.find('#'), line.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+// Lines of 16 space separated bytes
+pub fn to_plain(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        let values: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+        writeln!(text, "{}", values.join(" ")).unwrap();
+    }
+    text
+}
+
+// Pairs of hex digits, whitespace, commas, 0x prefixes and comments are ignored
+pub fn from_plain(text: &str) -> Result<Vec<u8>, DiskError> {
+    let mut bytes = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let digits: String = strip_comment(line)
+            .replace("0x", "")
+            .replace("0X", "")
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .collect();
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(parse_error(
+                line_number,
+                format!("invalid hex digit '{}'", c),
+            ));
+        }
+        if !digits.len().is_multiple_of(2) {
+            return Err(parse_error(line_number, "odd number of hex digits"));
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            let pair = std::str::from_utf8(pair).unwrap();
+            bytes.push(u8::from_str_radix(pair, 16).unwrap());
+        }
+    }
+    Ok(bytes)
+}
+
+// Intel HEX records start with ':', plain hex doesn't
+pub fn is_intel(text: &str) -> bool {
+    text.lines()
+        .map(|line| strip_comment(line).trim())
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with(':'))
+}
+
+// Intel HEX with the bytes placed at their memory address
+pub fn to_intel(bytes: &[u8], address: usize) -> String {
+    let mut text = String::new();
+    let mut upper = 0;
+    for (index, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let line_address = address + index * BYTES_PER_LINE;
+        // Addresses above 64 KiB need the upper 16 bits in their own record
+        if line_address >> 16 != upper {
+            upper = line_address >> 16;
+            let upper_bytes = (upper as u16).to_be_bytes();
+            write_record(&mut text, 0, RECORD_EXTENDED_LINEAR_ADDRESS, &upper_bytes);
+        }
+        write_record(&mut text, line_address as u16, RECORD_DATA, line);
+    }
+    write_record(&mut text, 0, RECORD_END_OF_FILE, &[]);
+    text
+}
+
+fn write_record(text: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let [high, low] = address.to_be_bytes();
+    let mut record = vec![data.len() as u8, high, low, record_type];
+    record.extend_from_slice(data);
+    let checksum = record
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg();
+    record.push(checksum);
+
+    text.push(':');
+    for byte in record {
+        write!(text, "{:02X}", byte).unwrap();
+    }
+    text.push('\n');
+}
+
+// Intel HEX into the at most max bytes from address on, gaps are filled with zeros.
+// Blank lines and comments between the records are ignored.
+pub fn from_intel(text: &str, address: usize, max: usize) -> Result<Vec<u8>, DiskError> {
+    let mut bytes = Vec::new();
+    let mut base = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = match line.strip_prefix(':') {
+            Some(record) => from_plain(record)
+                .map_err(|_| parse_error(line_number, "record is not made of hex digit pairs"))?,
+            None => return Err(parse_error(line_number, "record does not start with ':'")),
+        };
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(parse_error(line_number, "record length does not match"));
+        }
+        if record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(parse_error(line_number, "checksum mismatch"));
+        }
+
+        let offset = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            RECORD_DATA => {
+                let start = match (base + offset).checked_sub(address) {
+                    Some(start) => start,
+                    None => {
+                        let message = format!("data below {:#05x}", address);
+                        return Err(parse_error(line_number, message));
+                    }
+                };
+                // Extended addresses reach up to 4 GiB, refuse them before allocating
+                let end = start + data.len();
+                if end > max {
+                    return Err(DiskError::TooLarge { size: end, max });
+                }
+                if bytes.len() < end {
+                    bytes.resize(end, 0);
+                }
+                bytes[start..end].copy_from_slice(data);
+            }
+            RECORD_END_OF_FILE => return Ok(bytes),
+            RECORD_EXTENDED_SEGMENT_ADDRESS if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 4;
+            }
+            RECORD_EXTENDED_LINEAR_ADDRESS if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 16;
+            }
+            // The start address doesn't matter, programs start at the program start of the layout
+            RECORD_START_SEGMENT_ADDRESS | RECORD_START_LINEAR_ADDRESS => {}
+            record_type => {
+                let message = format!("unsupported record type {:02X}", record_type);
+                return Err(parse_error(line_number, message));
+            }
+        }
+    }
+    Err(parse_error(
+        text.lines().count(),
+        "missing end of file record",
+    ))
+}
@@ -1,31 +1,60 @@
+#[cfg(feature = "piston")]
 use piston_window::Key;
 
+#[cfg(feature = "piston")]
 use super::Cpu;
 
-pub fn handle_input(cpu: &mut Cpu, key: Key) {
-    //println!("{:?}", key);
-    let input = match key {
-        Key::D1 => 0x1,
-        Key::D2 => 0x2,
-        Key::D3 => 0x3,
-        Key::D4 => 0xc,
-        Key::Q => 0x4,
-        Key::W => 0x5,
-        Key::E => 0x6,
-        Key::R => 0xd,
-        Key::A => 0x7,
-        Key::S => 0x8,
-        Key::D => 0x9,
-        Key::F => 0xe,
-        Key::Y => 0xa,
-        Key::Z => 0xa,
-        Key::X => 0x0,
-        Key::C => 0xb,
-        Key::V => 0xf,
-        _ => 0x0,
-    };
+#[cfg(test)]
+#[path = "./tests/input.rs"]
+mod tests;
 
-    if input != 0x0 {
-        cpu.key_pressed(input);
+// Keypad key for a character of the left hand side of a QWERTY keyboard:
+//   1 2 3 4        1 2 3 C
+//   Q W E R   ->   4 5 6 D
+//   A S D F        7 8 9 E
+//   Z X C V        A 0 B F
+// Y works as well as Z for QWERTZ keyboards.
+pub fn keypad_key(c: char) -> Option<u8> {
+    match c.to_ascii_lowercase() {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xc),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xd),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xe),
+        'y' | 'z' => Some(0xa),
+        'x' => Some(0x0),
+        'c' => Some(0xb),
+        'v' => Some(0xf),
+        _ => None,
+    }
+}
+
+// Character of the keyboard key mapped to a keypad key, the inverse of keypad_key
+pub fn keyboard_char(key: u8) -> char {
+    const LAYOUT: [char; 16] = [
+        'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+    ];
+    LAYOUT[(key & 0xF) as usize]
+}
+
+// Forward a piston key press or release to the keypad
+#[cfg(feature = "piston")]
+pub fn handle_input(cpu: &mut Cpu, key: Key, pressed: bool) {
+    // Piston key codes of digits and letters are their ASCII characters
+    let input = char::from_u32(key as u32).and_then(keypad_key);
+
+    if let Some(input) = input {
+        if pressed {
+            cpu.key_pressed(input);
+        } else {
+            cpu.key_released(input);
+        }
     }
 }
@@ -0,0 +1,325 @@
+use std::fmt;
+
+use super::Platform;
+
+#[cfg(test)]
+#[path = "./tests/instruction.rs"]
+mod tests;
+
+// A decoded opcode. x and y are register numbers, kk a byte, nnn an address and n a nibble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // CHIP-8
+    Sys { nnn: u16 },             // 0NNN
+    Cls,                          // 00E0
+    Ret,                          // 00EE
+    Jump { nnn: u16 },            // 1NNN
+    Call { nnn: u16 },            // 2NNN
+    SkipEqImm { x: u8, kk: u8 },  // 3XKK
+    SkipNeImm { x: u8, kk: u8 },  // 4XKK
+    SkipEqReg { x: u8, y: u8 },   // 5XY0
+    LoadImm { x: u8, kk: u8 },    // 6XKK
+    AddImm { x: u8, kk: u8 },     // 7XKK
+    Move { x: u8, y: u8 },        // 8XY0
+    Or { x: u8, y: u8 },          // 8XY1
+    And { x: u8, y: u8 },         // 8XY2
+    Xor { x: u8, y: u8 },         // 8XY3
+    AddReg { x: u8, y: u8 },      // 8XY4
+    Sub { x: u8, y: u8 },         // 8XY5
+    ShiftRight { x: u8, y: u8 },  // 8XY6
+    SubReverse { x: u8, y: u8 },  // 8XY7
+    ShiftLeft { x: u8, y: u8 },   // 8XYE
+    SkipNeReg { x: u8, y: u8 },   // 9XY0
+    LoadI { nnn: u16 },           // ANNN
+    JumpOffset { nnn: u16 },      // BNNN
+    Random { x: u8, kk: u8 },     // CXKK
+    Draw { x: u8, y: u8, n: u8 }, // DXYN
+    SkipKey { x: u8 },            // EX9E
+    SkipNotKey { x: u8 },         // EXA1
+    LoadDelay { x: u8 },          // FX07
+    WaitKey { x: u8 },            // FX0A
+    SetDelay { x: u8 },           // FX15
+    SetSound { x: u8 },           // FX18
+    AddI { x: u8 },               // FX1E
+    LoadFont { x: u8 },           // FX29
+    StoreBcd { x: u8 },           // FX33
+    Store { x: u8 },              // FX55
+    Load { x: u8 },               // FX65
+    // SCHIP
+    ScrollDown { n: u8 },  // 00CN
+    ScrollRight,           // 00FB
+    ScrollLeft,            // 00FC
+    Exit,                  // 00FD
+    LowRes,                // 00FE
+    HighRes,               // 00FF
+    LoadBigFont { x: u8 }, // FX30
+    StoreFlags { x: u8 },  // FX75
+    LoadFlags { x: u8 },   // FX85
+    // XO-CHIP
+    ScrollUp { n: u8 },         // 00DN
+    SaveRange { x: u8, y: u8 }, // 5XY2
+    LoadRange { x: u8, y: u8 }, // 5XY3
+    LoadILong,                  // F000 NNNN, the address follows in the next word
+    Plane { n: u8 },            // FN01
+    Audio,                      // F002
+    Pitch { x: u8 },            // FX3A
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown opcode {:04x}", self.opcode)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+        let unknown = Err(DecodeError { opcode });
+
+        let instruction = match opcode & 0xF000 {
+            0x0000 => match nnn {
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                0x00C0..=0x00CF => Instruction::ScrollDown { n },
+                0x00D0..=0x00DF => Instruction::ScrollUp { n },
+                0x00FB => Instruction::ScrollRight,
+                0x00FC => Instruction::ScrollLeft,
+                0x00FD => Instruction::Exit,
+                0x00FE => Instruction::LowRes,
+                0x00FF => Instruction::HighRes,
+                _ => Instruction::Sys { nnn },
+            },
+            0x1000 => Instruction::Jump { nnn },
+            0x2000 => Instruction::Call { nnn },
+            0x3000 => Instruction::SkipEqImm { x, kk },
+            0x4000 => Instruction::SkipNeImm { x, kk },
+            0x5000 => match n {
+                0x0 => Instruction::SkipEqReg { x, y },
+                0x2 => Instruction::SaveRange { x, y },
+                0x3 => Instruction::LoadRange { x, y },
+                _ => return unknown,
+            },
+            0x6000 => Instruction::LoadImm { x, kk },
+            0x7000 => Instruction::AddImm { x, kk },
+            0x8000 => match n {
+                0x0 => Instruction::Move { x, y },
+                0x1 => Instruction::Or { x, y },
+                0x2 => Instruction::And { x, y },
+                0x3 => Instruction::Xor { x, y },
+                0x4 => Instruction::AddReg { x, y },
+                0x5 => Instruction::Sub { x, y },
+                0x6 => Instruction::ShiftRight { x, y },
+                0x7 => Instruction::SubReverse { x, y },
+                0xE => Instruction::ShiftLeft { x, y },
+                _ => return unknown,
+            },
+            0x9000 => match n {
+                0x0 => Instruction::SkipNeReg { x, y },
+                _ => return unknown,
+            },
+            0xA000 => Instruction::LoadI { nnn },
+            0xB000 => Instruction::JumpOffset { nnn },
+            0xC000 => Instruction::Random { x, kk },
+            0xD000 => Instruction::Draw { x, y, n },
+            0xE000 => match kk {
+                0x9E => Instruction::SkipKey { x },
+                0xA1 => Instruction::SkipNotKey { x },
+                _ => return unknown,
+            },
+            _ => match kk {
+                0x00 if x == 0 => Instruction::LoadILong,
+                0x01 => Instruction::Plane { n: x },
+                0x02 if x == 0 => Instruction::Audio,
+                0x07 => Instruction::LoadDelay { x },
+                0x0A => Instruction::WaitKey { x },
+                0x15 => Instruction::SetDelay { x },
+                0x18 => Instruction::SetSound { x },
+                0x1E => Instruction::AddI { x },
+                0x29 => Instruction::LoadFont { x },
+                0x30 => Instruction::LoadBigFont { x },
+                0x33 => Instruction::StoreBcd { x },
+                0x3A => Instruction::Pitch { x },
+                0x55 => Instruction::Store { x },
+                0x65 => Instruction::Load { x },
+                0x75 => Instruction::StoreFlags { x },
+                0x85 => Instruction::LoadFlags { x },
+                _ => return unknown,
+            },
+        };
+        Ok(instruction)
+    }
+
+    pub fn encode(&self) -> u16 {
+        let xy = |high: u16, x: u8, y: u8, low: u16| high | (x as u16) << 8 | (y as u16) << 4 | low;
+        let xkk = |high: u16, x: u8, kk: u8| high | (x as u16) << 8 | kk as u16;
+
+        match *self {
+            Instruction::Sys { nnn } => nnn & 0x0FFF,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jump { nnn } => 0x1000 | (nnn & 0x0FFF),
+            Instruction::Call { nnn } => 0x2000 | (nnn & 0x0FFF),
+            Instruction::SkipEqImm { x, kk } => xkk(0x3000, x, kk),
+            Instruction::SkipNeImm { x, kk } => xkk(0x4000, x, kk),
+            Instruction::SkipEqReg { x, y } => xy(0x5000, x, y, 0x0),
+            Instruction::LoadImm { x, kk } => xkk(0x6000, x, kk),
+            Instruction::AddImm { x, kk } => xkk(0x7000, x, kk),
+            Instruction::Move { x, y } => xy(0x8000, x, y, 0x0),
+            Instruction::Or { x, y } => xy(0x8000, x, y, 0x1),
+            Instruction::And { x, y } => xy(0x8000, x, y, 0x2),
+            Instruction::Xor { x, y } => xy(0x8000, x, y, 0x3),
+            Instruction::AddReg { x, y } => xy(0x8000, x, y, 0x4),
+            Instruction::Sub { x, y } => xy(0x8000, x, y, 0x5),
+            Instruction::ShiftRight { x, y } => xy(0x8000, x, y, 0x6),
+            Instruction::SubReverse { x, y } => xy(0x8000, x, y, 0x7),
+            Instruction::ShiftLeft { x, y } => xy(0x8000, x, y, 0xE),
+            Instruction::SkipNeReg { x, y } => xy(0x9000, x, y, 0x0),
+            Instruction::LoadI { nnn } => 0xA000 | (nnn & 0x0FFF),
+            Instruction::JumpOffset { nnn } => 0xB000 | (nnn & 0x0FFF),
+            Instruction::Random { x, kk } => xkk(0xC000, x, kk),
+            Instruction::Draw { x, y, n } => xy(0xD000, x, y, (n & 0xF) as u16),
+            Instruction::SkipKey { x } => xkk(0xE000, x, 0x9E),
+            Instruction::SkipNotKey { x } => xkk(0xE000, x, 0xA1),
+            Instruction::LoadDelay { x } => xkk(0xF000, x, 0x07),
+            Instruction::WaitKey { x } => xkk(0xF000, x, 0x0A),
+            Instruction::SetDelay { x } => xkk(0xF000, x, 0x15),
+            Instruction::SetSound { x } => xkk(0xF000, x, 0x18),
+            Instruction::AddI { x } => xkk(0xF000, x, 0x1E),
+            Instruction::LoadFont { x } => xkk(0xF000, x, 0x29),
+            Instruction::StoreBcd { x } => xkk(0xF000, x, 0x33),
+            Instruction::Store { x } => xkk(0xF000, x, 0x55),
+            Instruction::Load { x } => xkk(0xF000, x, 0x65),
+            Instruction::ScrollDown { n } => 0x00C0 | (n & 0xF) as u16,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::LoadBigFont { x } => xkk(0xF000, x, 0x30),
+            Instruction::StoreFlags { x } => xkk(0xF000, x, 0x75),
+            Instruction::LoadFlags { x } => xkk(0xF000, x, 0x85),
+            Instruction::ScrollUp { n } => 0x00D0 | (n & 0xF) as u16,
+            Instruction::SaveRange { x, y } => xy(0x5000, x, y, 0x2),
+            Instruction::LoadRange { x, y } => xy(0x5000, x, y, 0x3),
+            Instruction::LoadILong => 0xF000,
+            Instruction::Plane { n } => xkk(0xF000, n, 0x01),
+            Instruction::Audio => 0xF002,
+            Instruction::Pitch { x } => xkk(0xF000, x, 0x3A),
+        }
+    }
+
+    // Decode for a platform that only knows its own and older instructions.
+    // 00xx words of newer platforms stay machine code calls, as on the original CHIP-8.
+    pub fn decode_for(opcode: u16, platform: Platform) -> Result<Instruction, DecodeError> {
+        let instruction = Instruction::decode(opcode)?;
+        if instruction.platform() <= platform {
+            Ok(instruction)
+        } else if opcode & 0xF000 == 0x0000 {
+            Ok(Instruction::Sys {
+                nnn: opcode & 0x0FFF,
+            })
+        } else {
+            Err(DecodeError { opcode })
+        }
+    }
+
+    // Oldest platform that supports the instruction
+    pub fn platform(&self) -> Platform {
+        match self {
+            Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::LowRes
+            | Instruction::HighRes
+            | Instruction::LoadBigFont { .. }
+            | Instruction::StoreFlags { .. }
+            | Instruction::LoadFlags { .. } => Platform::SuperChip,
+            Instruction::ScrollUp { .. }
+            | Instruction::SaveRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::LoadILong
+            | Instruction::Plane { .. }
+            | Instruction::Audio
+            | Instruction::Pitch { .. } => Platform::XoChip,
+            _ => Platform::Chip8,
+        }
+    }
+
+    // Size in bytes, including the address following F000
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LoadILong => 4,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Sys { nnn } => write!(f, "SYS 0x{:03X}", nnn),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipEqImm { x, kk } => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipNeImm { x, kk } => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipEqReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LoadImm { x, kk } => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            Instruction::AddImm { x, kk } => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            Instruction::Move { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubReverse { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNeReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadI { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpOffset { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Random { x, kk } => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKey { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::StoreBcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::Store { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::Load { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::ScrollDown { n } => write!(f, "SCD {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::LoadBigFont { x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::StoreFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+            Instruction::ScrollUp { n } => write!(f, "SCU {}", n),
+            Instruction::SaveRange { x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange { x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LoadILong => write!(f, "LD I, LONG"),
+            Instruction::Plane { n } => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::Pitch { x } => write!(f, "PITCH V{:X}", x),
+        }
+    }
+}
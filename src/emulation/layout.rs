@@ -0,0 +1,161 @@
+use std::fmt;
+
+use super::Platform;
+
+#[cfg(test)]
+#[path = "./tests/layout.rs"]
+mod tests;
+
+// Sizes of the built-in fonts, 16 digits of 5 and 10 bytes
+const FONT_SIZE: usize = 80;
+const BIG_FONT_SIZE: usize = 160;
+
+// Registers hold 16 bit addresses
+const MAX_RAM_SIZE: usize = 0x10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    // More ram than 16 bit addresses reach
+    RamTooLarge {
+        ram_size: usize,
+    },
+    // A font or the program start lies outside of ram
+    OutOfRange {
+        name: &'static str,
+        address: usize,
+    },
+    // The small and the big font share bytes
+    FontsOverlap,
+    // A font lies where the program is loaded
+    FontInProgram {
+        name: &'static str,
+    },
+    // The program would be loaded into the interpreter area
+    ProgramInReservedArea {
+        program_start: usize,
+        reserved: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::RamTooLarge { ram_size } => write!(
+                f,
+                "ram size {:#x} is larger than the {:#x} bytes addresses reach",
+                ram_size, MAX_RAM_SIZE
+            ),
+            LayoutError::OutOfRange { name, address } => {
+                write!(f, "{} at {:04x} lies outside of ram", name, address)
+            }
+            LayoutError::FontsOverlap => write!(f, "fonts overlap"),
+            LayoutError::FontInProgram { name } => {
+                write!(f, "{} lies in the program area", name)
+            }
+            LayoutError::ProgramInReservedArea {
+                program_start,
+                reserved,
+            } => write!(
+                f,
+                "program start {:04x} lies in the reserved area below {:04x}",
+                program_start, reserved
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+// Where things live in the address space of the interpreter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    // Addressable memory in bytes
+    pub ram_size: usize,
+    // Programs are loaded and started here
+    pub program_start: usize,
+    // 4x5 hex digits used by Fx29
+    pub font_address: usize,
+    // SCHIP 8x10 digits used by Fx30
+    pub big_font_address: usize,
+    // Memory below this address belongs to the interpreter and can't be written,
+    // nothing is reserved by default like in most modern interpreters
+    pub reserved: usize,
+}
+
+impl MemoryLayout {
+    // Programs at 0x200 with the fonts in the interpreter area in front of them
+    pub fn for_platform(platform: Platform) -> MemoryLayout {
+        MemoryLayout {
+            ram_size: platform.ram_size(),
+            program_start: 0x200,
+            font_address: 0x000,
+            big_font_address: 0x050,
+            reserved: 0,
+        }
+    }
+
+    // ETI 660 computers start programs at 0x600
+    pub const ETI_660: MemoryLayout = MemoryLayout {
+        ram_size: 0x1000,
+        program_start: 0x600,
+        font_address: 0x000,
+        big_font_address: 0x050,
+        reserved: 0,
+    };
+
+    // Look up a layout by name, e.g. from the command line.
+    // Named layouts keep their program start and get the memory of the platform.
+    pub fn from_name(name: &str, platform: Platform) -> Option<MemoryLayout> {
+        let layout = match name.to_lowercase().as_str() {
+            "default" => MemoryLayout::for_platform(platform),
+            "eti660" | "eti-660" => MemoryLayout::ETI_660,
+            _ => return None,
+        };
+        Some(MemoryLayout {
+            ram_size: platform.ram_size(),
+            ..layout
+        })
+    }
+
+    // Largest program that fits behind the program start
+    pub fn max_program_size(&self) -> usize {
+        self.ram_size.saturating_sub(self.program_start)
+    }
+
+    // Fonts and program have to fit into ram without overlapping
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        if self.ram_size > MAX_RAM_SIZE {
+            return Err(LayoutError::RamTooLarge {
+                ram_size: self.ram_size,
+            });
+        }
+        let areas = [
+            ("font", self.font_address, FONT_SIZE),
+            ("big font", self.big_font_address, BIG_FONT_SIZE),
+            ("program start", self.program_start, 1),
+        ];
+        for (name, address, size) in areas {
+            if address + size > self.ram_size {
+                return Err(LayoutError::OutOfRange { name, address });
+            }
+        }
+        let font = self.font_address..self.font_address + FONT_SIZE;
+        let big_font = self.big_font_address..self.big_font_address + BIG_FONT_SIZE;
+        if font.start < big_font.end && big_font.start < font.end {
+            return Err(LayoutError::FontsOverlap);
+        }
+        let program = self.program_start..self.ram_size;
+        for (name, area) in [("font", font), ("big font", big_font)] {
+            if area.start < program.end && program.start < area.end {
+                return Err(LayoutError::FontInProgram { name });
+            }
+        }
+        if self.program_start < self.reserved {
+            return Err(LayoutError::ProgramInReservedArea {
+                program_start: self.program_start,
+                reserved: self.reserved,
+            });
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,51 @@
+pub mod asm;
+mod browser;
+mod cartridge;
 mod cpu;
+pub mod disasm;
 mod disk;
+#[cfg(feature = "piston")]
 mod display;
+mod flicker;
+mod framebuffer;
+mod hex;
 mod input;
+mod instruction;
+mod layout;
+pub mod octo;
+mod palette;
+mod platform;
+mod quirks;
+mod record;
+mod romdb;
+mod scheduler;
+pub mod screenshot;
+mod settings;
+mod terminal;
+pub mod text;
+mod viewport;
+mod watch;
 
-pub use self::cpu::Cpu;
-pub use self::disk::Disk;
+pub use self::browser::{RomBrowser, RomEntry};
+pub use self::cartridge::{Cartridge, CartridgeError};
+pub use self::cpu::{Cpu, CpuError};
+pub use self::disk::{Disk, DiskError};
+#[cfg(feature = "piston")]
 pub use self::display::Display;
+pub use self::flicker::{AntiFlicker, FrameBlender};
+pub use self::framebuffer::{Framebuffer, Resolution};
+#[cfg(feature = "piston")]
 pub use self::input::handle_input;
+pub use self::input::{keyboard_char, keypad_key};
+pub use self::instruction::{DecodeError, Instruction};
+pub use self::layout::{LayoutError, MemoryLayout};
+pub use self::palette::{parse_colour, Palette};
+pub use self::platform::Platform;
+pub use self::quirks::Quirks;
+pub use self::record::{record_frames, GifRecorder, RecordError};
+pub use self::romdb::{sha1_hex, RomInfo};
+pub use self::scheduler::{Scheduler, DEFAULT_INSTRUCTIONS_PER_FRAME, FRAME_RATE};
+pub use self::settings::{Settings, SettingsError};
+pub use self::terminal::{KeyTimeouts, TextRendering};
+pub use self::viewport::{viewport, Scaling};
+pub use self::watch::FileWatcher;
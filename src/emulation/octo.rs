@@ -0,0 +1,1344 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::asm::AsmError;
+use super::Instruction;
+
+#[cfg(test)]
+#[path = "./tests/octo.rs"]
+mod tests;
+
+// Octo programs are loaded and started at this address
+const PROGRAM_START: usize = 0x200;
+// Largest program that fits behind 0x200 in the 64 KiB XO-CHIP address space
+const MAX_PROGRAM_SIZE: usize = 0x10000 - PROGRAM_START;
+// Macros calling themselves would expand forever
+const MAX_EXPANSIONS: usize = 100_000;
+
+// Words with a meaning of their own, they can't name labels, constants or macros
+const KEYWORDS: &[&str] = &[
+    ":",
+    ":alias",
+    ":const",
+    ":org",
+    ":next",
+    ":unpack",
+    ":breakpoint",
+    ":monitor",
+    ":proto",
+    ":macro",
+    ":calc",
+    ":byte",
+    ":pointer",
+    ":call",
+    ":stringmode",
+    ":assert",
+    ";",
+    "return",
+    "clear",
+    "bcd",
+    "save",
+    "load",
+    "saveflags",
+    "loadflags",
+    "delay",
+    "buzzer",
+    "pitch",
+    "if",
+    "then",
+    "begin",
+    "else",
+    "end",
+    "key",
+    "-key",
+    "while",
+    "loop",
+    "again",
+    "jump",
+    "jump0",
+    "native",
+    "sprite",
+    "plane",
+    "audio",
+    "scroll-down",
+    "scroll-up",
+    "scroll-right",
+    "scroll-left",
+    "exit",
+    "lores",
+    "hires",
+    "i",
+    "hex",
+    "bighex",
+    "long",
+    "random",
+    ":=",
+    "+=",
+    "-=",
+    "|=",
+    "&=",
+    "^=",
+    "=-",
+    ">>=",
+    "<<=",
+    "{",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    // Quoted text, never a keyword or a name
+    string: bool,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn is(&self, text: &str) -> bool {
+        !self.string && self.text == text
+    }
+}
+
+fn error(token: &Token, message: impl Into<String>) -> AsmError {
+    AsmError {
+        line: token.line,
+        column: token.column,
+        message: message.into(),
+    }
+}
+
+// Value of an operand, labels may be defined further down
+enum Value {
+    Known(i64),
+    Label(Token),
+}
+
+// How a label address is patched into the program once it is known
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Patch {
+    // Low 12 bits of an instruction
+    Address,
+    // A whole 16 bit word
+    Long,
+    // Low nibble of a byte gets bits 8 to 11
+    HighNibble,
+    // A byte gets bits 8 to 15
+    HighByte,
+    // A byte gets bits 0 to 7
+    LowByte,
+}
+
+struct Fixup {
+    address: usize,
+    patch: Patch,
+    label: Token,
+}
+
+// Compare operators of if and while, with the register on the left
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Key,
+    NotKey,
+}
+
+impl Compare {
+    fn from_token(token: &Token) -> Option<Compare> {
+        if token.string {
+            return None;
+        }
+        match token.text.as_str() {
+            "==" => Some(Compare::Eq),
+            "!=" => Some(Compare::Ne),
+            "<" => Some(Compare::Lt),
+            ">" => Some(Compare::Gt),
+            "<=" => Some(Compare::Le),
+            ">=" => Some(Compare::Ge),
+            "key" => Some(Compare::Key),
+            "-key" => Some(Compare::NotKey),
+            _ => None,
+        }
+    }
+
+    fn inverse(self) -> Compare {
+        match self {
+            Compare::Eq => Compare::Ne,
+            Compare::Ne => Compare::Eq,
+            Compare::Lt => Compare::Ge,
+            Compare::Gt => Compare::Le,
+            Compare::Le => Compare::Gt,
+            Compare::Ge => Compare::Lt,
+            Compare::Key => Compare::NotKey,
+            Compare::NotKey => Compare::Key,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+    None,
+}
+
+struct Condition {
+    x: u8,
+    compare: Compare,
+    operand: Operand,
+}
+
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>,
+    calls: usize,
+}
+
+// Body of a :stringmode for the characters of its alphabet
+struct StringMode {
+    alphabet: Vec<char>,
+    body: Vec<Token>,
+}
+
+// if ... begin waiting for its else or end
+struct Block {
+    // Jump over the block that is patched at the else or end
+    jump: usize,
+    has_else: bool,
+    token: Token,
+}
+
+// loop waiting for its again
+struct Loop {
+    start: usize,
+    // Jumps out of the loop of the while statements in it
+    exits: Vec<usize>,
+    token: Token,
+}
+
+struct Compiler {
+    tokens: VecDeque<Token>,
+    // Position errors at the end of the program are reported at
+    end: Token,
+    rom: Vec<u8>,
+    here: usize,
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    string_modes: HashMap<String, Vec<StringMode>>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    loops: Vec<Loop>,
+    expansions: usize,
+    // 0x200 is kept free for a jump to main unless main comes first
+    jump_to_main: bool,
+}
+
+// Compile Octo source into a ROM that is loaded at 0x200, the way Octo and
+// its GIF cartridges do.
+//
+//   : main                  label, the program starts here
+//   v0 := 5  v1 += v0       register operations, i := label, i += v0, ...
+//   if v0 == 5 then v1 := 0
+//   if v0 key begin ... else ... end
+//   loop ... while v0 != 0 ... again
+//   sprite v0 v1 5          and the other statements of the Octo manual
+//   0xFF 0b10000001         numbers on their own are data bytes
+//   draw-player             a name on its own calls the subroutine
+//
+// :const, :alias, :calc, :macro and :stringmode work like in Octo, :calc
+// expressions are evaluated right to left. Comments start with '#'.
+pub fn compile(source: &str) -> Result<Vec<u8>, AsmError> {
+    let tokens = lex(source)?;
+    let end = Token {
+        text: String::new(),
+        string: false,
+        line: source.lines().count().max(1),
+        column: 1,
+    };
+    let mut compiler = Compiler {
+        tokens: tokens.into(),
+        end,
+        rom: vec![0, 0],
+        here: PROGRAM_START + 2,
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::from([
+            ("unpack-hi".to_string(), 0x0),
+            ("unpack-lo".to_string(), 0x1),
+            ("compare-temp".to_string(), 0xF),
+        ]),
+        macros: HashMap::new(),
+        string_modes: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+        loops: Vec::new(),
+        expansions: 0,
+        jump_to_main: true,
+    };
+    while let Some(token) = compiler.tokens.pop_front() {
+        compiler.statement(token)?;
+    }
+    compiler.finish()
+}
+
+// Tokens are separated by whitespace, strings are quoted
+fn lex(source: &str) -> Result<Vec<Token>, AsmError> {
+    let mut tokens = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            if chars[i].is_whitespace() {
+                i += 1;
+            } else if chars[i] == '#' {
+                break;
+            } else if chars[i] == '"' {
+                let mut string = String::new();
+                i += 1;
+                loop {
+                    let c = match chars.get(i) {
+                        Some(&c) => c,
+                        None => {
+                            let token = Token {
+                                text: String::new(),
+                                string: true,
+                                line,
+                                column,
+                            };
+                            return Err(error(&token, "unterminated string"));
+                        }
+                    };
+                    i += 1;
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            let escaped = chars.get(i).copied().unwrap_or('\\');
+                            i += 1;
+                            string.push(match escaped {
+                                't' => '\t',
+                                'n' => '\n',
+                                'r' => '\r',
+                                'v' => '\x0B',
+                                '0' => '\0',
+                                other => other,
+                            });
+                        }
+                        _ => string.push(c),
+                    }
+                }
+                tokens.push(Token {
+                    text: string,
+                    string: true,
+                    line,
+                    column,
+                });
+            } else {
+                let length = chars[i..].iter().take_while(|c| !c.is_whitespace()).count();
+                tokens.push(Token {
+                    text: chars[i..i + length].iter().collect(),
+                    string: false,
+                    line,
+                    column,
+                });
+                i += length;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Decimal, hex as 0x1F and binary as 0b0101, optionally negative
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn register_number(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|x| x as u8),
+        _ => None,
+    }
+}
+
+fn in_range(token: &Token, value: i64, min: i64, max: i64, what: &str) -> Result<i64, AsmError> {
+    if value < min || value > max {
+        return Err(error(token, format!("{} {} out of range", what, value)));
+    }
+    Ok(value)
+}
+
+impl Compiler {
+    fn next(&mut self) -> Result<Token, AsmError> {
+        self.tokens
+            .pop_front()
+            .ok_or_else(|| error(&self.end, "unexpected end of program"))
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens.front().is_some_and(|token| token.is(text))
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if !token.is(text) {
+            return Err(error(&token, format!("expected '{}'", text)));
+        }
+        Ok(token)
+    }
+
+    fn register(&self, token: &Token) -> Option<u8> {
+        if token.string {
+            return None;
+        }
+        self.aliases
+            .get(&token.text)
+            .copied()
+            .or_else(|| register_number(&token.text))
+    }
+
+    fn next_register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.register(&token).ok_or_else(|| {
+            error(
+                &token,
+                format!("expected a register, found '{}'", token.text),
+            )
+        })
+    }
+
+    // A name that can be defined, not a number, register or keyword
+    fn next_name(&mut self) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        let reserved = token.string
+            || token.text.starts_with(['{', '}', '(', ')'])
+            || parse_number(&token.text).is_some()
+            || self.register(&token).is_some()
+            || KEYWORDS.contains(&token.text.as_str());
+        if reserved {
+            return Err(error(
+                &token,
+                format!("'{}' is a reserved name", token.text),
+            ));
+        }
+        Ok(token)
+    }
+
+    fn define_label(&mut self, name: &Token, address: usize) -> Result<(), AsmError> {
+        if self.constants.contains_key(&name.text) || self.macros.contains_key(&name.text) {
+            return Err(error(name, format!("'{}' is already defined", name.text)));
+        }
+        if self.labels.insert(name.text.clone(), address).is_some() {
+            return Err(error(
+                name,
+                format!("label '{}' is already defined", name.text),
+            ));
+        }
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: &Token, value: f64) -> Result<(), AsmError> {
+        if self.labels.contains_key(&name.text) || self.macros.contains_key(&name.text) {
+            return Err(error(name, format!("'{}' is already defined", name.text)));
+        }
+        self.constants.insert(name.text.clone(), value);
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, AsmError> {
+        let token = self.next()?;
+        if token.is("{") {
+            return self.calc().map(|value| Value::Known(value.floor() as i64));
+        }
+        if let Some(value) = self.known_value(&token) {
+            return Ok(Value::Known(value));
+        }
+        if token.string
+            || self.register(&token).is_some()
+            || KEYWORDS.contains(&token.text.as_str())
+        {
+            return Err(error(
+                &token,
+                format!("expected a value, found '{}'", token.text),
+            ));
+        }
+        Ok(Value::Label(token))
+    }
+
+    // Numbers, constants and labels defined so far
+    fn known_value(&self, token: &Token) -> Option<i64> {
+        if token.string {
+            return None;
+        }
+        parse_number(&token.text)
+            .or_else(|| {
+                self.constants
+                    .get(&token.text)
+                    .map(|value| value.floor() as i64)
+            })
+            .or_else(|| self.labels.get(&token.text).map(|&address| address as i64))
+    }
+
+    // A value that is known right away, for bytes, nibbles and addresses of :org
+    fn constant_value(&mut self, min: i64, max: i64, what: &str) -> Result<i64, AsmError> {
+        let token = self.tokens.front().cloned().unwrap_or(self.end.clone());
+        match self.value()? {
+            Value::Known(value) => in_range(&token, value, min, max, what),
+            Value::Label(label) => Err(error(&label, format!("'{}' is not defined", label.text))),
+        }
+    }
+
+    // Negative bytes are stored as two's complement, e.g. v0 := -1
+    fn byte_value(&mut self) -> Result<u8, AsmError> {
+        self.constant_value(-0x80, 0xFF, "byte")
+            .map(|value| value as u8)
+    }
+
+    fn nibble_value(&mut self) -> Result<u8, AsmError> {
+        self.constant_value(0, 0xF, "nibble")
+            .map(|value| value as u8)
+    }
+
+    // Address of a jump, call or i :=, patched later for labels defined further down
+    fn address_value(&mut self) -> Result<u16, AsmError> {
+        let token = self.tokens.front().cloned().unwrap_or(self.end.clone());
+        match self.value()? {
+            Value::Known(value) => in_range(&token, value, 0, 0xFFF, "address").map(|a| a as u16),
+            Value::Label(label) => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    patch: Patch::Address,
+                    label,
+                });
+                Ok(0)
+            }
+        }
+    }
+
+    fn long_value(&mut self) -> Result<u16, AsmError> {
+        let token = self.tokens.front().cloned().unwrap_or(self.end.clone());
+        match self.value()? {
+            Value::Known(value) => in_range(&token, value, 0, 0xFFFF, "address").map(|a| a as u16),
+            Value::Label(label) => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    patch: Patch::Long,
+                    label,
+                });
+                Ok(0)
+            }
+        }
+    }
+
+    fn emit_byte(&mut self, byte: u8, token: &Token) -> Result<(), AsmError> {
+        if self.here >= PROGRAM_START + MAX_PROGRAM_SIZE {
+            return Err(error(token, "program does not fit into memory"));
+        }
+        let index = self.here - PROGRAM_START;
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16, token: &Token) -> Result<(), AsmError> {
+        let [high, low] = word.to_be_bytes();
+        self.emit_byte(high, token)?;
+        self.emit_byte(low, token)
+    }
+
+    fn emit(&mut self, instruction: Instruction, token: &Token) -> Result<(), AsmError> {
+        self.emit_word(instruction.encode(), token)
+    }
+
+    // Jump whose target is filled in by patch_jump
+    fn emit_jump_placeholder(&mut self, token: &Token) -> Result<usize, AsmError> {
+        let address = self.here;
+        self.emit(Instruction::Jump { nnn: 0 }, token)?;
+        Ok(address)
+    }
+
+    fn patch_jump(&mut self, address: usize, token: &Token) -> Result<(), AsmError> {
+        let target = in_range(token, self.here as i64, 0, 0xFFF, "address")? as u16;
+        let opcode = Instruction::Jump { nnn: target }.encode();
+        let index = address - PROGRAM_START;
+        self.rom[index..index + 2].copy_from_slice(&opcode.to_be_bytes());
+        Ok(())
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AsmError> {
+        if token.string {
+            return Err(error(&token, "unexpected string"));
+        }
+        if let Some(x) = self.register(&token) {
+            return self.register_statement(x, &token);
+        }
+        if self.macros.contains_key(&token.text) {
+            return self.expand_macro(&token);
+        }
+        if self.string_modes.contains_key(&token.text) {
+            return self.expand_string_mode(&token);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.next_name()?;
+                // Programs starting with main don't need the jump to it
+                let starts_program = self.here == PROGRAM_START + 2 && self.rom.len() == 2;
+                if name.text == "main" && self.jump_to_main && starts_program {
+                    self.jump_to_main = false;
+                    self.rom.clear();
+                    self.here = PROGRAM_START;
+                }
+                self.define_label(&name, self.here)?;
+            }
+            ":alias" => {
+                let name = self.next_name()?;
+                let x = self.next_register()?;
+                self.aliases.insert(name.text, x);
+            }
+            ":const" => {
+                let name = self.next_name()?;
+                let value = self.constant_value(i64::MIN, i64::MAX, "value")?;
+                self.define_constant(&name, value as f64)?;
+            }
+            ":calc" => {
+                let name = self.next_name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.define_constant(&name, value)?;
+            }
+            ":org" => {
+                let address = self.constant_value(0, 0xFFFF, "address")? as usize;
+                if address < PROGRAM_START {
+                    return Err(error(&token, "programs start at 0x200"));
+                }
+                self.here = address;
+            }
+            ":next" => {
+                let name = self.next_name()?;
+                self.define_label(&name, self.here + 1)?;
+            }
+            ":unpack" => self.unpack(&token)?,
+            ":breakpoint" | ":proto" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ":macro" => self.define_macro()?,
+            ":stringmode" => self.define_string_mode()?,
+            ":assert" => {
+                let message = match self.tokens.front() {
+                    Some(next) if next.string => self.next()?.text,
+                    _ => String::from("assertion failed"),
+                };
+                self.expect("{")?;
+                if self.calc()? == 0.0 {
+                    return Err(error(&token, message));
+                }
+            }
+            ":byte" => {
+                let byte = self.byte_value()?;
+                self.emit_byte(byte, &token)?;
+            }
+            ":pointer" => {
+                let address = self.long_value()?;
+                self.emit_word(address, &token)?;
+            }
+            ":call" => {
+                let nnn = self.address_value()?;
+                self.emit(Instruction::Call { nnn }, &token)?;
+            }
+            ";" | "return" => self.emit(Instruction::Ret, &token)?,
+            "clear" => self.emit(Instruction::Cls, &token)?,
+            "bcd" => {
+                let x = self.next_register()?;
+                self.emit(Instruction::StoreBcd { x }, &token)?;
+            }
+            "save" | "load" => {
+                let x = self.next_register()?;
+                let save = token.text == "save";
+                let instruction = if self.peek_is("-") {
+                    self.next()?;
+                    let y = self.next_register()?;
+                    match save {
+                        true => Instruction::SaveRange { x, y },
+                        false => Instruction::LoadRange { x, y },
+                    }
+                } else {
+                    match save {
+                        true => Instruction::Store { x },
+                        false => Instruction::Load { x },
+                    }
+                };
+                self.emit(instruction, &token)?;
+            }
+            "saveflags" => {
+                let x = self.next_register()?;
+                self.emit(Instruction::StoreFlags { x }, &token)?;
+            }
+            "loadflags" => {
+                let x = self.next_register()?;
+                self.emit(Instruction::LoadFlags { x }, &token)?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.next_register()?;
+                let instruction = match token.text.as_str() {
+                    "delay" => Instruction::SetDelay { x },
+                    "buzzer" => Instruction::SetSound { x },
+                    _ => Instruction::Pitch { x },
+                };
+                self.emit(instruction, &token)?;
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let next = self.next()?;
+                if next.is("then") {
+                    self.skip_unless(&condition, false, &token)?;
+                } else if next.is("begin") {
+                    self.skip_unless(&condition, true, &token)?;
+                    let jump = self.emit_jump_placeholder(&token)?;
+                    self.blocks.push(Block {
+                        jump,
+                        has_else: false,
+                        token,
+                    });
+                } else {
+                    return Err(error(&next, "expected 'then' or 'begin'"));
+                }
+            }
+            "else" => {
+                let (jump, has_else) = match self.blocks.last() {
+                    Some(block) => (block.jump, block.has_else),
+                    None => return Err(error(&token, "else without if ... begin")),
+                };
+                if has_else {
+                    return Err(error(&token, "second else in one if ... begin"));
+                }
+                let end = self.emit_jump_placeholder(&token)?;
+                self.patch_jump(jump, &token)?;
+                let block = self.blocks.last_mut().unwrap();
+                block.jump = end;
+                block.has_else = true;
+            }
+            "end" => match self.blocks.pop() {
+                Some(block) => self.patch_jump(block.jump, &token)?,
+                None => return Err(error(&token, "end without if ... begin")),
+            },
+            "loop" => self.loops.push(Loop {
+                start: self.here,
+                exits: Vec::new(),
+                token,
+            }),
+            "while" => {
+                if self.loops.is_empty() {
+                    return Err(error(&token, "while outside of a loop"));
+                }
+                let condition = self.condition()?;
+                self.skip_unless(&condition, true, &token)?;
+                let exit = self.emit_jump_placeholder(&token)?;
+                self.loops.last_mut().unwrap().exits.push(exit);
+            }
+            "again" => {
+                let repeat = match self.loops.pop() {
+                    Some(repeat) => repeat,
+                    None => return Err(error(&token, "again without loop")),
+                };
+                let nnn = in_range(&token, repeat.start as i64, 0, 0xFFF, "address")? as u16;
+                self.emit(Instruction::Jump { nnn }, &token)?;
+                for exit in repeat.exits {
+                    self.patch_jump(exit, &token)?;
+                }
+            }
+            "jump" => {
+                let nnn = self.address_value()?;
+                self.emit(Instruction::Jump { nnn }, &token)?;
+            }
+            "jump0" => {
+                let nnn = self.address_value()?;
+                self.emit(Instruction::JumpOffset { nnn }, &token)?;
+            }
+            "native" => {
+                let nnn = self.address_value()?;
+                self.emit(Instruction::Sys { nnn }, &token)?;
+            }
+            "sprite" => {
+                let x = self.next_register()?;
+                let y = self.next_register()?;
+                let n = self.nibble_value()?;
+                self.emit(Instruction::Draw { x, y, n }, &token)?;
+            }
+            "plane" => {
+                let n = self.nibble_value()?;
+                self.emit(Instruction::Plane { n }, &token)?;
+            }
+            "audio" => self.emit(Instruction::Audio, &token)?,
+            "scroll-down" => {
+                let n = self.nibble_value()?;
+                self.emit(Instruction::ScrollDown { n }, &token)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble_value()?;
+                self.emit(Instruction::ScrollUp { n }, &token)?;
+            }
+            "scroll-right" => self.emit(Instruction::ScrollRight, &token)?,
+            "scroll-left" => self.emit(Instruction::ScrollLeft, &token)?,
+            "exit" => self.emit(Instruction::Exit, &token)?,
+            "lores" => self.emit(Instruction::LowRes, &token)?,
+            "hires" => self.emit(Instruction::HighRes, &token)?,
+            "i" => self.index_statement(&token)?,
+            _ => {
+                // Numbers and constants are data, any other name is a subroutine
+                let is_label = self.labels.contains_key(&token.text);
+                match self.known_value(&token) {
+                    Some(value) if !is_label => {
+                        let byte = in_range(&token, value, -0x80, 0xFF, "byte")? as u8;
+                        self.emit_byte(byte, &token)?;
+                    }
+                    _ => {
+                        self.tokens.push_front(token.clone());
+                        let nnn = self.address_value()?;
+                        self.emit(Instruction::Call { nnn }, &token)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: u8, token: &Token) -> Result<(), AsmError> {
+        let operator = self.next()?;
+        let source = self.tokens.front().and_then(|next| self.register(next));
+        let instruction = match (operator.text.as_str(), source) {
+            (":=", _) if self.peek_is("key") => {
+                self.next()?;
+                Instruction::WaitKey { x }
+            }
+            (":=", _) if self.peek_is("delay") => {
+                self.next()?;
+                Instruction::LoadDelay { x }
+            }
+            (":=", _) if self.peek_is("random") => {
+                self.next()?;
+                let kk = self.byte_value()?;
+                Instruction::Random { x, kk }
+            }
+            (":=", Some(y)) => Instruction::Move { x, y },
+            (":=", None) => Instruction::LoadImm {
+                x,
+                kk: self.byte_value()?,
+            },
+            ("+=", Some(y)) => Instruction::AddReg { x, y },
+            ("+=", None) => Instruction::AddImm {
+                x,
+                kk: self.byte_value()?,
+            },
+            ("-=", Some(y)) => Instruction::Sub { x, y },
+            // Subtracting a byte adds its two's complement
+            ("-=", None) => Instruction::AddImm {
+                x,
+                kk: self.byte_value()?.wrapping_neg(),
+            },
+            ("|=", Some(y)) => Instruction::Or { x, y },
+            ("&=", Some(y)) => Instruction::And { x, y },
+            ("^=", Some(y)) => Instruction::Xor { x, y },
+            ("=-", Some(y)) => Instruction::SubReverse { x, y },
+            (">>=", Some(y)) => Instruction::ShiftRight { x, y },
+            ("<<=", Some(y)) => Instruction::ShiftLeft { x, y },
+            ("|=" | "&=" | "^=" | "=-" | ">>=" | "<<=", None) => {
+                let found = self.next()?;
+                return Err(error(
+                    &found,
+                    format!("expected a register, found '{}'", found.text),
+                ));
+            }
+            _ => {
+                let message = format!("unknown operator '{}'", operator.text);
+                return Err(error(&operator, message));
+            }
+        };
+        if source.is_some() {
+            self.next()?;
+        }
+        self.emit(instruction, token)
+    }
+
+    fn index_statement(&mut self, token: &Token) -> Result<(), AsmError> {
+        let operator = self.next()?;
+        if operator.is("+=") {
+            let x = self.next_register()?;
+            return self.emit(Instruction::AddI { x }, token);
+        }
+        if !operator.is(":=") {
+            return Err(error(&operator, "expected ':=' or '+='"));
+        }
+        if self.peek_is("hex") {
+            self.next()?;
+            let x = self.next_register()?;
+            self.emit(Instruction::LoadFont { x }, token)
+        } else if self.peek_is("bighex") {
+            self.next()?;
+            let x = self.next_register()?;
+            self.emit(Instruction::LoadBigFont { x }, token)
+        } else if self.peek_is("long") {
+            self.next()?;
+            self.emit(Instruction::LoadILong, token)?;
+            let nnnn = self.long_value()?;
+            self.emit_word(nnnn, token)
+        } else {
+            let nnn = self.address_value()?;
+            self.emit(Instruction::LoadI { nnn }, token)
+        }
+    }
+
+    // :unpack loads an address into the unpack-hi and unpack-lo registers, with a
+    // nibble in front of its top 4 bits or, with long, all of its 16 bits
+    fn unpack(&mut self, token: &Token) -> Result<(), AsmError> {
+        let (high_nibble, patch) = if self.peek_is("long") {
+            self.next()?;
+            (None, Patch::HighByte)
+        } else {
+            (Some(self.nibble_value()? << 4), Patch::HighNibble)
+        };
+        let high_register = self.aliases["unpack-hi"];
+        let low_register = self.aliases["unpack-lo"];
+        let (address, label) = match self.value()? {
+            Value::Known(value) => (in_range(token, value, 0, 0xFFFF, "address")?, None),
+            Value::Label(label) => (0, Some(label)),
+        };
+        let high = match high_nibble {
+            Some(nibble) => nibble | (address >> 8 & 0xF) as u8,
+            None => (address >> 8) as u8,
+        };
+        if let Some(label) = &label {
+            self.fixups.push(Fixup {
+                address: self.here + 1,
+                patch,
+                label: label.clone(),
+            });
+            self.fixups.push(Fixup {
+                address: self.here + 3,
+                patch: Patch::LowByte,
+                label: label.clone(),
+            });
+        }
+        let load_high = Instruction::LoadImm {
+            x: high_register,
+            kk: high,
+        };
+        let load_low = Instruction::LoadImm {
+            x: low_register,
+            kk: address as u8,
+        };
+        self.emit(load_high, token)?;
+        self.emit(load_low, token)
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.next_register()?;
+        let token = self.next()?;
+        let compare = Compare::from_token(&token)
+            .ok_or_else(|| error(&token, format!("unknown comparison '{}'", token.text)))?;
+        let operand = match compare {
+            Compare::Key | Compare::NotKey => Operand::None,
+            _ => match self.tokens.front().and_then(|next| self.register(next)) {
+                Some(y) => {
+                    self.next()?;
+                    Operand::Register(y)
+                }
+                None => Operand::Byte(self.byte_value()?),
+            },
+        };
+        Ok(Condition {
+            x,
+            compare,
+            operand,
+        })
+    }
+
+    // Skip the next instruction unless the condition holds, or if it holds when inverted.
+    // Relational compares subtract into compare-temp and test the borrow in VF.
+    fn skip_unless(
+        &mut self,
+        condition: &Condition,
+        inverted: bool,
+        token: &Token,
+    ) -> Result<(), AsmError> {
+        let x = condition.x;
+        let compare = match inverted {
+            true => condition.compare.inverse(),
+            false => condition.compare,
+        };
+        let instruction = match (compare, condition.operand) {
+            (Compare::Key, _) => Instruction::SkipNotKey { x },
+            (Compare::NotKey, _) => Instruction::SkipKey { x },
+            (Compare::Eq, Operand::Register(y)) => Instruction::SkipNeReg { x, y },
+            (Compare::Eq, Operand::Byte(kk)) => Instruction::SkipNeImm { x, kk },
+            (Compare::Ne, Operand::Register(y)) => Instruction::SkipEqReg { x, y },
+            (Compare::Ne, Operand::Byte(kk)) => Instruction::SkipEqImm { x, kk },
+            (_, operand) => {
+                let temp = self.aliases["compare-temp"];
+                let load = match operand {
+                    Operand::Register(y) => Instruction::Move { x: temp, y },
+                    Operand::Byte(kk) => Instruction::LoadImm { x: temp, kk },
+                    Operand::None => unreachable!("relational compares have an operand"),
+                };
+                // x - operand borrows for <, operand - x for >
+                let subtract = match compare {
+                    Compare::Lt | Compare::Ge => Instruction::SubReverse { x: temp, y: x },
+                    _ => Instruction::Sub { x: temp, y: x },
+                };
+                self.emit(load, token)?;
+                self.emit(subtract, token)?;
+                match compare {
+                    Compare::Lt | Compare::Gt => Instruction::SkipNeImm { x: 0xF, kk: 0 },
+                    _ => Instruction::SkipEqImm { x: 0xF, kk: 0 },
+                }
+            }
+        };
+        self.emit(instruction, token)
+    }
+
+    // Tokens up to the matching '}', the '{' is already taken
+    fn block_tokens(&mut self) -> Result<Vec<Token>, AsmError> {
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self.next()?;
+            if token.is("{") {
+                depth += 1;
+            } else if token.is("}") {
+                if depth == 0 {
+                    return Ok(body);
+                }
+                depth -= 1;
+            }
+            body.push(token);
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.next_name()?;
+        let mut args = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.is("{") {
+                break;
+            }
+            args.push(token.text);
+        }
+        let body = self.block_tokens()?;
+        if self.labels.contains_key(&name.text) || self.constants.contains_key(&name.text) {
+            return Err(error(&name, format!("'{}' is already defined", name.text)));
+        }
+        self.macros.insert(
+            name.text,
+            Macro {
+                args,
+                body,
+                calls: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn define_string_mode(&mut self) -> Result<(), AsmError> {
+        let name = self.next_name()?;
+        let alphabet = self.next()?;
+        if !alphabet.string {
+            return Err(error(&alphabet, "expected the alphabet as a string"));
+        }
+        self.expect("{")?;
+        let body = self.block_tokens()?;
+        self.string_modes
+            .entry(name.text)
+            .or_default()
+            .push(StringMode {
+                alphabet: alphabet.text.chars().collect(),
+                body,
+            });
+        Ok(())
+    }
+
+    // Put the body in front of the remaining tokens
+    fn push_expansion(&mut self, body: Vec<Token>, token: &Token) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(error(
+                token,
+                "macros expand too often, do they call themselves?",
+            ));
+        }
+        for expanded in body.into_iter().rev() {
+            self.tokens.push_front(expanded);
+        }
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, token: &Token) -> Result<(), AsmError> {
+        let arg_count = self.macros[&token.text].args.len();
+        let mut values = Vec::with_capacity(arg_count);
+        for _ in 0..arg_count {
+            values.push(self.next()?);
+        }
+        let definition = self.macros.get_mut(&token.text).unwrap();
+        let calls = definition.calls;
+        definition.calls += 1;
+
+        let body = definition
+            .body
+            .iter()
+            .map(|body_token| {
+                let arg = definition.args.iter().position(|arg| body_token.is(arg));
+                match arg {
+                    Some(index) => values[index].clone(),
+                    None if body_token.is("CALLS") => Token {
+                        text: calls.to_string(),
+                        ..body_token.clone()
+                    },
+                    None => body_token.clone(),
+                }
+            })
+            .collect();
+        self.push_expansion(body, token)
+    }
+
+    // The body of the string mode once for every character of the string, with
+    // CHAR, INDEX and VALUE for the character, its index in the string and in the alphabet
+    fn expand_string_mode(&mut self, token: &Token) -> Result<(), AsmError> {
+        let text = self.next()?;
+        if !text.string {
+            return Err(error(&text, "expected a string"));
+        }
+        let mut body = Vec::new();
+        for (index, c) in text.text.chars().enumerate() {
+            let (value, mode) = self.string_modes[&token.text]
+                .iter()
+                .find_map(|mode| {
+                    let value = mode.alphabet.iter().position(|&letter| letter == c)?;
+                    Some((value, mode))
+                })
+                .ok_or_else(|| error(&text, format!("'{}' is not in the alphabet", c)))?;
+            for body_token in &mode.body {
+                let replacement = match body_token.text.as_str() {
+                    _ if body_token.string => None,
+                    "CHAR" => Some(c as u32 as usize),
+                    "INDEX" => Some(index),
+                    "VALUE" => Some(value),
+                    _ => None,
+                };
+                body.push(match replacement {
+                    Some(number) => Token {
+                        text: number.to_string(),
+                        ..body_token.clone()
+                    },
+                    None => body_token.clone(),
+                });
+            }
+        }
+        self.push_expansion(body, token)
+    }
+
+    // Expression of a :calc, :assert or { } value, the '{' is already taken
+    fn calc(&mut self) -> Result<f64, AsmError> {
+        let tokens = self.block_tokens()?;
+        let mut position = 0;
+        let value = self.calc_expression(&tokens, &mut position)?;
+        if let Some(extra) = tokens.get(position) {
+            return Err(error(
+                extra,
+                format!("unexpected '{}' in expression", extra.text),
+            ));
+        }
+        Ok(value)
+    }
+
+    // No precedence, operators are applied right to left like in Octo
+    fn calc_expression(&self, tokens: &[Token], position: &mut usize) -> Result<f64, AsmError> {
+        let left = self.calc_term(tokens, position)?;
+        let operator = match tokens.get(*position) {
+            Some(operator) if !operator.string && operator.text != ")" => operator,
+            _ => return Ok(left),
+        };
+        *position += 1;
+        let right = self.calc_expression(tokens, position)?;
+        let int = |value: f64| value as i64;
+        Ok(match operator.text.as_str() {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => (int(left) & int(right)) as f64,
+            "|" => (int(left) | int(right)) as f64,
+            "^" => (int(left) ^ int(right)) as f64,
+            "<<" => (int(left) << (int(right) & 63)) as f64,
+            ">>" => (int(left) >> (int(right) & 63)) as f64,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as i64 as f64,
+            ">" => (left > right) as i64 as f64,
+            "<=" => (left <= right) as i64 as f64,
+            ">=" => (left >= right) as i64 as f64,
+            "==" => (left == right) as i64 as f64,
+            "!=" => (left != right) as i64 as f64,
+            _ => {
+                let message = format!("unknown operator '{}'", operator.text);
+                return Err(error(operator, message));
+            }
+        })
+    }
+
+    fn calc_term(&self, tokens: &[Token], position: &mut usize) -> Result<f64, AsmError> {
+        let token = match tokens.get(*position) {
+            Some(token) => token,
+            None => {
+                let last = tokens.last().unwrap_or(&self.end);
+                return Err(error(last, "incomplete expression"));
+            }
+        };
+        *position += 1;
+        if token.string {
+            return Err(error(token, "unexpected string in expression"));
+        }
+        let unary = |value: f64| -> Option<f64> {
+            Some(match token.text.as_str() {
+                "-" => -value,
+                "~" => !(value as i64) as f64,
+                "!" => (value == 0.0) as i64 as f64,
+                "sin" => value.sin(),
+                "cos" => value.cos(),
+                "tan" => value.tan(),
+                "exp" => value.exp(),
+                "log" => value.ln(),
+                "abs" => value.abs(),
+                "sqrt" => value.sqrt(),
+                "sign" => value.signum() * (value != 0.0) as i64 as f64,
+                "ceil" => value.ceil(),
+                "floor" => value.floor(),
+                "@" => {
+                    let index = (value as usize).checked_sub(PROGRAM_START)?;
+                    self.rom.get(index).copied().unwrap_or(0) as f64
+                }
+                _ => return None,
+            })
+        };
+        match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression(tokens, position)?;
+                match tokens.get(*position) {
+                    Some(close) if close.is(")") => *position += 1,
+                    _ => return Err(error(token, "missing ')'")),
+                }
+                Ok(value)
+            }
+            "strlen" => match tokens.get(*position) {
+                Some(string) if string.string => {
+                    *position += 1;
+                    Ok(string.text.chars().count() as f64)
+                }
+                _ => Err(error(token, "strlen expects a string")),
+            },
+            "-" | "~" | "!" | "sin" | "cos" | "tan" | "exp" | "log" | "abs" | "sqrt" | "sign"
+            | "ceil" | "floor" | "@" => {
+                let value = self.calc_term(tokens, position)?;
+                Ok(unary(value).unwrap_or(0.0))
+            }
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            "HERE" => Ok(self.here as f64),
+            text => {
+                if let Some(value) = self.constants.get(text) {
+                    return Ok(*value);
+                }
+                if let Some(x) = self.register(token) {
+                    return Ok(x as f64);
+                }
+                match self.known_value(token) {
+                    Some(value) => Ok(value as f64),
+                    None => match text.parse::<f64>() {
+                        Ok(value) => Ok(value),
+                        Err(_) => Err(error(token, format!("'{}' is not defined", text))),
+                    },
+                }
+            }
+        }
+    }
+
+    // Check for open blocks, patch the labels and the jump to main
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        if let Some(block) = self.blocks.last() {
+            return Err(error(&block.token, "if ... begin without end"));
+        }
+        if let Some(open) = self.loops.last() {
+            return Err(error(&open.token, "loop without again"));
+        }
+
+        if self.jump_to_main {
+            if !self.labels.contains_key("main") {
+                return Err(error(&self.end, "program has no main label"));
+            }
+            let main = Token {
+                text: String::from("main"),
+                ..self.end.clone()
+            };
+            self.fixups.push(Fixup {
+                address: PROGRAM_START,
+                patch: Patch::Address,
+                label: main,
+            });
+            self.rom[0] = 0x10;
+        }
+
+        for fixup in &self.fixups {
+            let address = match self.labels.get(&fixup.label.text) {
+                Some(&address) => address,
+                None => {
+                    let message = format!("'{}' is not defined", fixup.label.text);
+                    return Err(error(&fixup.label, message));
+                }
+            };
+            let index = fixup.address - PROGRAM_START;
+            let rom = &mut self.rom;
+            match fixup.patch {
+                Patch::Address => {
+                    let nnn = in_range(&fixup.label, address as i64, 0, 0xFFF, "address")?;
+                    rom[index] = rom[index] & 0xF0 | (nnn >> 8) as u8;
+                    rom[index + 1] = nnn as u8;
+                }
+                Patch::Long => {
+                    rom[index..index + 2].copy_from_slice(&(address as u16).to_be_bytes())
+                }
+                Patch::HighNibble => rom[index] = rom[index] & 0xF0 | (address >> 8 & 0xF) as u8,
+                Patch::HighByte => rom[index] = (address >> 8) as u8,
+                Patch::LowByte => rom[index] = address as u8,
+            }
+        }
+        Ok(self.rom)
+    }
+}
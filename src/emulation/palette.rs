@@ -0,0 +1,86 @@
+#[cfg(test)]
+#[path = "./tests/palette.rs"]
+mod tests;
+
+// Colours as 0xRRGGBB for the background, plane 1, plane 2 and pixels set in both planes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub colours: [u32; 4],
+}
+
+// Built-in themes, the first one is used unless the settings pick another
+const THEMES: [(&str, [u32; 4]); 5] = [
+    // Dark blue with teal, orange and yellow
+    ("default", [0x1A1C2C, 0x257179, 0xEF7D57, 0xFFCD75]),
+    // Green phosphor monitor
+    ("green", [0x0A140A, 0x33FF66, 0x1A8033, 0xB3FFC6]),
+    // Amber phosphor monitor
+    ("amber", [0x140C00, 0xFFB000, 0x995C00, 0xFFDD99]),
+    // Handheld LCD
+    ("lcd", [0x9BBC0F, 0x0F380F, 0x5E7F1C, 0x306230]),
+    ("high-contrast", [0x000000, 0xFFFFFF, 0xFFFF00, 0x00FFFF]),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::themes().remove(0)
+    }
+}
+
+impl Palette {
+    pub fn themes() -> Vec<Palette> {
+        THEMES
+            .iter()
+            .map(|(name, colours)| Palette {
+                name: name.to_string(),
+                colours: *colours,
+            })
+            .collect()
+    }
+
+    // Look up a built-in theme by name
+    pub fn from_name(name: &str) -> Option<Palette> {
+        let name = name.to_lowercase();
+        Palette::themes()
+            .into_iter()
+            .find(|theme| theme.name == name)
+    }
+
+    // Colours separated by whitespace or commas, e.g. "#000000 #FFFFFF".
+    // Two colours are enough for programs that only draw to the first plane,
+    // the second plane then shares their foreground colour.
+    pub fn parse(name: &str, text: &str) -> Result<Palette, String> {
+        let mut colours = Vec::new();
+        for part in text.split(|c: char| c.is_whitespace() || c == ',') {
+            if part.is_empty() {
+                continue;
+            }
+            match parse_colour(part) {
+                Some(colour) => colours.push(colour),
+                None => return Err(format!("invalid colour '{}'", part)),
+            }
+        }
+        let colours = match colours[..] {
+            [back, front] => [back, front, front, front],
+            [back, front, plane2, blend] => [back, front, plane2, blend],
+            _ => return Err(format!("expected 2 or 4 colours, got {}", colours.len())),
+        };
+        Ok(Palette {
+            name: name.to_string(),
+            colours,
+        })
+    }
+}
+
+// Hex colours like "#FFCC00", "0xFFCC00" or "FFCC00"
+pub fn parse_colour(text: &str) -> Option<u32> {
+    let hex = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix("0x"))
+        .unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
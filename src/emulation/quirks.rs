@@ -0,0 +1,77 @@
+// Behaviour of the ambiguous opcodes that differs between CHIP-8 interpreters.
+// All quirks off is the original behaviour of this emulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    // 8xy6/8xyE shift Vy into Vx instead of shifting Vx in place
+    pub shift_uses_vy: bool,
+    // Fx55/Fx65 leave I pointing behind the last register accessed
+    pub load_store_increments_i: bool,
+    // With load_store_increments_i, I is advanced by x instead of x + 1 and ends on Vx
+    pub load_store_increments_i_by_x: bool,
+    // Bnnn jumps to nnn + Vx (x = highest nibble of nnn) instead of nnn + V0
+    pub jump_uses_vx: bool,
+    // 8xy1/8xy2/8xy3 reset VF to 0
+    pub vf_reset: bool,
+    // Sprites are clipped at the screen edges instead of wrapping around
+    pub clip_sprites: bool,
+    // Dxyn waits for the next vertical blank before the program continues
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    // Original COSMAC VIP interpreter
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_increments_i_by_x: false,
+        jump_uses_vx: false,
+        vf_reset: true,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    // CHIP-48 on the HP-48 calculators, which leaves I one short after Fx55/Fx65
+    pub const CHIP48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: true,
+        load_store_increments_i_by_x: true,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    // SUPER-CHIP 1.1
+    pub const SCHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        load_store_increments_i_by_x: false,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    // Modern interpreters like Octo and XO-CHIP
+    pub const MODERN: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_increments_i_by_x: false,
+        jump_uses_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    // Look up a preset by name, e.g. from the command line
+    pub fn from_name(name: &str) -> Option<Quirks> {
+        match name.to_lowercase().as_str() {
+            "vip" | "cosmac" | "chip8" => Some(Quirks::COSMAC_VIP),
+            "chip48" => Some(Quirks::CHIP48),
+            "schip" | "superchip" => Some(Quirks::SCHIP),
+            "modern" | "octo" | "xochip" => Some(Quirks::MODERN),
+            "none" => Some(Quirks::default()),
+            _ => None,
+        }
+    }
+}
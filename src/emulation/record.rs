@@ -0,0 +1,177 @@
+use std::{fmt, io, io::Write};
+
+use super::{Cpu, CpuError, Framebuffer, Scheduler, FRAME_RATE};
+
+#[cfg(test)]
+#[path = "./tests/record.rs"]
+mod tests;
+
+// Browsers slow down GIF frames shown for less than 2/100 s
+const MIN_DELAY: u64 = 2;
+
+#[derive(Debug)]
+pub enum RecordError {
+    // The GIF could not be encoded or written
+    Gif(String),
+    // The program stopped during a headless recording
+    Cpu(CpuError),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Gif(err) => write!(f, "recording could not be written: {}", err),
+            RecordError::Cpu(err) => write!(f, "recording stopped by the cpu: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<gif::EncodingError> for RecordError {
+    fn from(err: gif::EncodingError) -> Self {
+        RecordError::Gif(err.to_string())
+    }
+}
+
+impl From<io::Error> for RecordError {
+    fn from(err: io::Error) -> Self {
+        RecordError::Gif(err.to_string())
+    }
+}
+
+impl From<CpuError> for RecordError {
+    fn from(err: CpuError) -> Self {
+        RecordError::Cpu(err)
+    }
+}
+
+// Records framebuffer frames at 60 Hz into an animated GIF with the palette of a theme.
+// Frames that don't change the picture only make the previous frame last longer.
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: usize,
+    height: usize,
+    // Palette indices of the frame still being shown and for how many 60 Hz frames
+    pending: Option<(Vec<u8>, u64)>,
+    // 60 Hz frames and hundredths of a second written so far, to keep rounding errors
+    // from adding up
+    frames_written: u64,
+    centiseconds_written: u64,
+}
+
+impl<W: Write> GifRecorder<W> {
+    // The picture is width by height pixels, framebuffers of another size are scaled to it
+    pub fn new(
+        writer: W,
+        width: u16,
+        height: u16,
+        palette: &[u32; 4],
+    ) -> Result<Self, RecordError> {
+        let colours: Vec<u8> = palette
+            .iter()
+            .flat_map(|colour| colour.to_be_bytes()[1..].to_vec())
+            .collect();
+        let mut encoder = gif::Encoder::new(writer, width, height, &colours)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(GifRecorder {
+            encoder,
+            width: width as usize,
+            height: height as usize,
+            pending: None,
+            frames_written: 0,
+            centiseconds_written: 0,
+        })
+    }
+
+    // Recorder for framebuffers of the current size, every pixel scale times as large
+    pub fn for_framebuffer(
+        writer: W,
+        framebuffer: &Framebuffer,
+        scale: u16,
+        palette: &[u32; 4],
+    ) -> Result<Self, RecordError> {
+        let scale = scale.max(1);
+        let width = framebuffer.width() as u16 * scale;
+        let height = framebuffer.height() as u16 * scale;
+        GifRecorder::new(writer, width, height, palette)
+    }
+
+    // Add the framebuffer as the next 60 Hz frame
+    pub fn push(&mut self, framebuffer: &Framebuffer) -> Result<(), RecordError> {
+        let pixels = self.scale(framebuffer);
+        match self.pending.take() {
+            Some((shown, frames)) if shown == pixels => {
+                self.pending = Some((shown, frames + 1));
+            }
+            Some((shown, frames)) => {
+                // A frame too short to show is replaced by the next one
+                if self.delay(frames) < MIN_DELAY {
+                    self.pending = Some((pixels, frames + 1));
+                } else {
+                    self.write(&shown, frames)?;
+                    self.pending = Some((pixels, 1));
+                }
+            }
+            None => self.pending = Some((pixels, 1)),
+        }
+        Ok(())
+    }
+
+    // Write the last frame and hand back the writer
+    pub fn finish(mut self) -> Result<W, RecordError> {
+        if let Some((shown, frames)) = self.pending.take() {
+            self.write(&shown, frames)?;
+        }
+        Ok(self.encoder.into_inner()?)
+    }
+
+    // Hundredths of a second the next frames last, from the rounded time of all frames
+    fn delay(&self, frames: u64) -> u64 {
+        let end =
+            ((self.frames_written + frames) * 100 + FRAME_RATE as u64 / 2) / FRAME_RATE as u64;
+        end - self.centiseconds_written
+    }
+
+    fn write(&mut self, pixels: &[u8], frames: u64) -> Result<(), RecordError> {
+        let delay = self.delay(frames).max(MIN_DELAY);
+        let mut frame = gif::Frame::from_indexed_pixels(
+            self.width as u16,
+            self.height as u16,
+            pixels.to_vec(),
+            None,
+        );
+        frame.delay = delay.min(u16::MAX as u64) as u16;
+        self.encoder.write_frame(&frame)?;
+        self.frames_written += frames;
+        self.centiseconds_written += delay;
+        Ok(())
+    }
+
+    // Palette indices of the framebuffer resized to the picture, nearest neighbour
+    fn scale(&self, framebuffer: &Framebuffer) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            let source_y = y * framebuffer.height() / self.height;
+            for x in 0..self.width {
+                let source_x = x * framebuffer.width() / self.width;
+                pixels.push(framebuffer.pixel(source_x, source_y) & 0x3);
+            }
+        }
+        pixels
+    }
+}
+
+// Run a program headless for a number of frames, recording every frame
+pub fn record_frames<W: Write>(
+    cpu: &mut Cpu,
+    scheduler: &mut Scheduler,
+    recorder: &mut GifRecorder<W>,
+    frames: u32,
+) -> Result<(), RecordError> {
+    for _ in 0..frames {
+        scheduler.run_frame(cpu)?;
+        recorder.push(&cpu.framebuffer)?;
+    }
+    Ok(())
+}
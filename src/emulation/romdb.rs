@@ -0,0 +1,87 @@
+use sha1::{Digest, Sha1};
+
+use super::{Platform, Quirks, DEFAULT_INSTRUCTIONS_PER_FRAME};
+
+#[cfg(test)]
+#[path = "./tests/romdb.rs"]
+mod tests;
+
+// Settings a known ROM needs, looked up by the SHA-1 of its bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RomInfo {
+    pub sha1: &'static str,
+    pub title: &'static str,
+    pub author: &'static str,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    // Background, plane 1, plane 2 and both planes as 0xRRGGBB
+    pub colours: Option<[u32; 4]>,
+    // What the keypad keys used by the program do
+    pub keys: &'static [(u8, &'static str)],
+}
+
+const ROMS: [RomInfo; 4] = [
+    RomInfo {
+        sha1: "a82ca5c53e1dcedfab4f65efef02229145771b7d",
+        title: "CHIP-8 Logo",
+        author: "unknown",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+        colours: None,
+        keys: &[],
+    },
+    RomInfo {
+        sha1: "1ba58656810b67fd131eb9af3e3987863bf26c90",
+        title: "IBM Logo",
+        author: "unknown",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+        colours: None,
+        keys: &[],
+    },
+    RomInfo {
+        sha1: "b9272ae1acdaaa79ab649f6b48b72088ca2b1d74",
+        title: "Maze",
+        author: "David Winter",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+        colours: None,
+        keys: &[],
+    },
+    RomInfo {
+        sha1: "5f518084744bf3cb8733f6e5454dfd1634320563",
+        title: "Tetris",
+        author: "Fran Dachille",
+        // Written for the CHIP-48 interpreter on the HP-48
+        platform: Platform::Chip8,
+        quirks: Quirks::CHIP48,
+        instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+        colours: None,
+        keys: &[
+            (0x4, "rotate"),
+            (0x5, "move left"),
+            (0x6, "move right"),
+            (0x1, "drop"),
+        ],
+    },
+];
+
+impl RomInfo {
+    // Entry for the ROM, if it is a known one
+    pub fn lookup(rom: &[u8]) -> Option<&'static RomInfo> {
+        let sha1 = sha1_hex(rom);
+        ROMS.iter().find(|info| info.sha1 == sha1)
+    }
+}
+
+// SHA-1 of the ROM bytes as lower case hex
+pub fn sha1_hex(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
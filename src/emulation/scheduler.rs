@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use super::{Cpu, CpuError};
+
+#[cfg(test)]
+#[path = "./tests/scheduler.rs"]
+mod tests;
+
+// Timers and the display run at 60 Hz
+pub const FRAME_RATE: u32 = 60;
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+// Don't try to catch up more than this many frames after a stall
+const MAX_FRAMES_PER_ADVANCE: u32 = 10;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+pub struct Scheduler {
+    pub instructions_per_frame: u32,
+    // Elapsed time not yet run, in nanoseconds times FRAME_RATE
+    pending: u128,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_INSTRUCTIONS_PER_FRAME)
+    }
+}
+
+impl Scheduler {
+    pub fn new(instructions_per_frame: u32) -> Self {
+        Scheduler {
+            instructions_per_frame,
+            pending: 0,
+        }
+    }
+
+    // Run a single frame: the instruction budget followed by one timer tick.
+    // Headless runs can call this directly instead of advance.
+    pub fn run_frame(&mut self, cpu: &mut Cpu) -> Result<(), CpuError> {
+        cpu.vblank();
+        for _ in 0..self.instructions_per_frame {
+            cpu.step()?;
+        }
+        cpu.tick_timers();
+        Ok(())
+    }
+
+    // Run as many frames as fit into the wall clock time elapsed since the last call.
+    // Returns the number of frames run.
+    pub fn advance(&mut self, cpu: &mut Cpu, elapsed: Duration) -> Result<u32, CpuError> {
+        self.pending += elapsed.as_nanos() * FRAME_RATE as u128;
+
+        let mut frames = 0;
+        while self.pending >= NANOS_PER_SECOND {
+            self.pending -= NANOS_PER_SECOND;
+            if frames == MAX_FRAMES_PER_ADVANCE {
+                self.pending %= NANOS_PER_SECOND;
+                break;
+            }
+            self.run_frame(cpu)?;
+            frames += 1;
+        }
+        Ok(frames)
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::Framebuffer;
+
+#[cfg(test)]
+#[path = "./tests/screenshot.rs"]
+mod tests;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    // The image could not be encoded
+    Encoding(String),
+    // The file could not be written
+    Io(io::Error),
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScreenshotError::Encoding(err) => write!(f, "screenshot could not be encoded: {}", err),
+            ScreenshotError::Io(err) => write!(f, "screenshot could not be written: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+impl From<io::Error> for ScreenshotError {
+    fn from(err: io::Error) -> Self {
+        ScreenshotError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(err: png::EncodingError) -> Self {
+        ScreenshotError::Encoding(err.to_string())
+    }
+}
+
+// PNG of the framebuffer in the colours of a 0xRRGGBB palette,
+// every pixel becomes a square of scale by scale pixels
+pub fn to_png(
+    framebuffer: &Framebuffer,
+    palette: &[u32; 4],
+    scale: u32,
+) -> Result<Vec<u8>, ScreenshotError> {
+    let scale = scale.max(1) as usize;
+    let width = framebuffer.width() * scale;
+    let height = framebuffer.height() * scale;
+
+    let rgba = framebuffer.to_rgba(palette);
+    let mut scaled = Vec::with_capacity(width * height * 4);
+    for row in rgba.chunks(framebuffer.width() * 4) {
+        let mut line = Vec::with_capacity(width * 4);
+        for pixel in row.chunks(4) {
+            for _ in 0..scale {
+                line.extend_from_slice(pixel);
+            }
+        }
+        for _ in 0..scale {
+            scaled.extend_from_slice(&line);
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&scaled)?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+// Binary PBM at native resolution, pixels set in any plane are black
+pub fn to_pbm(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut bytes = format!("P4\n{} {}\n", framebuffer.width(), framebuffer.height()).into_bytes();
+    for row in framebuffer.rows() {
+        // Eight pixels per byte with the leftmost in the highest bit
+        for pixels in row.chunks(8) {
+            let byte = pixels
+                .iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel != 0)
+                .fold(0u8, |byte, (bit, _)| byte | 0x80 >> bit);
+            bytes.push(byte);
+        }
+    }
+    bytes
+}
+
+pub fn save_png(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    palette: &[u32; 4],
+    scale: u32,
+) -> Result<(), ScreenshotError> {
+    fs::write(path, to_png(framebuffer, palette, scale)?)?;
+    Ok(())
+}
+
+pub fn save_pbm(path: &Path, framebuffer: &Framebuffer) -> Result<(), ScreenshotError> {
+    fs::write(path, to_pbm(framebuffer))?;
+    Ok(())
+}
+
+// First of dir/name-1.extension, dir/name-2.extension, ... that doesn't exist yet
+pub fn next_free_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    (1..)
+        .map(|number| dir.join(format!("{}-{}.{}", name, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
@@ -0,0 +1,181 @@
+use std::{fmt, fs, io, path::Path};
+
+use super::{AntiFlicker, Palette, Scaling};
+
+#[cfg(test)]
+#[path = "./tests/settings.rs"]
+mod tests;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    // A line could not be understood
+    Parse { line: usize, message: String },
+    // The file could not be read
+    Io(io::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Parse { line, message } => {
+                write!(f, "settings invalid in line {}: {}", line, message)
+            }
+            SettingsError::Io(err) => write!(f, "settings could not be read: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(err: io::Error) -> Self {
+        SettingsError::Io(err)
+    }
+}
+
+// Frontend settings from a text file of "key = value" lines, '#' followed by a space starts
+// a comment so colours like #FFCC00 aren't one:
+//
+//   anti_flicker = blend   # off, or, blend
+//   blend_frames = 4
+//   palette.paper = #FFFFFF #000000 #FF0000 #0000FF
+//   theme = paper          # default, green, amber, lcd, high-contrast or a palette
+//   screenshot_scale = 4
+//   recording_scale = 2
+//   scaling = fit          # integer, fit
+//   fullscreen = true
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub anti_flicker: AntiFlicker,
+    // Palettes defined in the settings, cycled through after the built-in themes
+    pub palettes: Vec<Palette>,
+    // Theme or palette to start with, it wins over the colours of the ROM database
+    pub theme: Option<String>,
+    // Screen pixels per CHIP-8 pixel of PNG screenshots
+    pub screenshot_scale: u32,
+    // Same for GIF recordings
+    pub recording_scale: u16,
+    pub scaling: Scaling,
+    // Start in fullscreen instead of a window
+    pub fullscreen: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            anti_flicker: AntiFlicker::Off,
+            palettes: Vec::new(),
+            theme: None,
+            screenshot_scale: 8,
+            recording_scale: 4,
+            scaling: Scaling::Integer,
+            fullscreen: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+        Settings::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Settings, SettingsError> {
+        let mut settings = Settings::default();
+        let mut blend_frames = None;
+        let mut theme_line = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| SettingsError::Parse {
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(error(format!("expected key = value, got '{}'", line))),
+            };
+
+            match key {
+                "anti_flicker" => match AntiFlicker::from_name(value) {
+                    Some(mode) => settings.anti_flicker = mode,
+                    None => return Err(error(format!("unknown anti-flicker mode '{}'", value))),
+                },
+                "blend_frames" => match value.parse::<u8>() {
+                    Ok(frames) if frames > 0 => blend_frames = Some(frames),
+                    _ => return Err(error(format!("invalid frame count '{}'", value))),
+                },
+                "screenshot_scale" => match value.parse::<u32>() {
+                    Ok(scale) if (1..=64).contains(&scale) => settings.screenshot_scale = scale,
+                    _ => return Err(error(format!("invalid screenshot scale '{}'", value))),
+                },
+                "recording_scale" => match value.parse::<u16>() {
+                    Ok(scale) if (1..=64).contains(&scale) => settings.recording_scale = scale,
+                    _ => return Err(error(format!("invalid recording scale '{}'", value))),
+                },
+                "scaling" => match Scaling::from_name(value) {
+                    Some(scaling) => settings.scaling = scaling,
+                    None => return Err(error(format!("unknown scaling '{}'", value))),
+                },
+                "fullscreen" => match value.parse::<bool>() {
+                    Ok(fullscreen) => settings.fullscreen = fullscreen,
+                    Err(_) => {
+                        return Err(error(format!("expected true or false, got '{}'", value)))
+                    }
+                },
+                "theme" => {
+                    settings.theme = Some(value.to_lowercase());
+                    theme_line = index + 1;
+                }
+                _ => match key.strip_prefix("palette.") {
+                    Some(name) if !name.is_empty() => {
+                        let palette = Palette::parse(&name.to_lowercase(), value).map_err(error)?;
+                        settings.palettes.retain(|p| p.name != palette.name);
+                        settings.palettes.push(palette);
+                    }
+                    _ => return Err(error(format!("unknown setting '{}'", key))),
+                },
+            }
+        }
+
+        // The frame count may come before or after the mode
+        if let (AntiFlicker::Blend { frames }, Some(count)) =
+            (&mut settings.anti_flicker, blend_frames)
+        {
+            *frames = count;
+        }
+        // Palettes may be defined after the theme picking them
+        if let Some(theme) = &settings.theme {
+            if !settings.all_palettes().iter().any(|p| &p.name == theme) {
+                return Err(SettingsError::Parse {
+                    line: theme_line,
+                    message: format!("unknown theme '{}'", theme),
+                });
+            }
+        }
+        Ok(settings)
+    }
+
+    // Built-in themes followed by the palettes of the settings
+    pub fn all_palettes(&self) -> Vec<Palette> {
+        let mut palettes = Palette::themes();
+        palettes.retain(|theme| !self.palettes.iter().any(|p| p.name == theme.name));
+        palettes.extend(self.palettes.iter().cloned());
+        palettes
+    }
+}
+
+// Text in front of a '#' that starts the line or is followed by whitespace
+fn strip_comment(line: &str) -> &str {
+    let end = line.char_indices().find(|(index, c)| {
+        *c == '#'
+            && (*index == 0
+                || line[index + 1..]
+                    .chars()
+                    .next()
+                    .is_none_or(char::is_whitespace))
+    });
+    &line[..end.map_or(line.len(), |(index, _)| index)]
+}
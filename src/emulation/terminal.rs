@@ -0,0 +1,114 @@
+use super::{Cpu, Framebuffer};
+
+#[cfg(test)]
+#[path = "./tests/terminal.rs"]
+mod tests;
+
+// How framebuffer pixels become characters, pixels set in any plane are lit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextRendering {
+    // One character for 1x2 pixels using the half block characters
+    #[default]
+    HalfBlock,
+    // One character for 2x4 pixels using the braille patterns, four times smaller
+    Braille,
+}
+
+impl TextRendering {
+    pub fn from_name(name: &str) -> Option<TextRendering> {
+        match name.to_lowercase().as_str() {
+            "halfblock" | "half-block" => Some(TextRendering::HalfBlock),
+            "braille" => Some(TextRendering::Braille),
+            _ => None,
+        }
+    }
+
+    // Lines of text showing the framebuffer
+    pub fn render(&self, framebuffer: &Framebuffer) -> Vec<String> {
+        let lit = |x: usize, y: usize| {
+            x < framebuffer.width() && y < framebuffer.height() && framebuffer.pixel(x, y) != 0
+        };
+        match self {
+            TextRendering::HalfBlock => (0..framebuffer.height())
+                .step_by(2)
+                .map(|y| {
+                    (0..framebuffer.width())
+                        .map(|x| match (lit(x, y), lit(x, y + 1)) {
+                            (true, true) => '\u{2588}',
+                            (true, false) => '\u{2580}',
+                            (false, true) => '\u{2584}',
+                            (false, false) => ' ',
+                        })
+                        .collect()
+                })
+                .collect(),
+            TextRendering::Braille => (0..framebuffer.height())
+                .step_by(4)
+                .map(|y| {
+                    (0..framebuffer.width())
+                        .step_by(2)
+                        .map(|x| {
+                            let dots = BRAILLE_DOTS
+                                .iter()
+                                .filter(|(dx, dy, _)| lit(x + dx, y + dy))
+                                .fold(0, |dots, (_, _, bit)| dots | bit);
+                            char::from_u32(0x2800 + dots).unwrap()
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+// Offset in the 2x4 cell and bit of every braille dot
+const BRAILLE_DOTS: [(usize, usize, u32); 8] = [
+    (0, 0, 0x01),
+    (0, 1, 0x02),
+    (0, 2, 0x04),
+    (1, 0, 0x08),
+    (1, 1, 0x10),
+    (1, 2, 0x20),
+    (0, 3, 0x40),
+    (1, 3, 0x80),
+];
+
+// Terminals in raw mode usually only report key presses, so a pressed keypad key
+// is released again after a number of frames unless the key repeats
+pub struct KeyTimeouts {
+    pub hold_frames: u32,
+    // Frames until each keypad key is released, 0 while it is up
+    remaining: [u32; 16],
+}
+
+impl KeyTimeouts {
+    pub fn new(hold_frames: u32) -> Self {
+        KeyTimeouts {
+            hold_frames,
+            remaining: [0; 16],
+        }
+    }
+
+    pub fn press(&mut self, cpu: &mut Cpu, key: u8) {
+        cpu.key_pressed(key);
+        self.remaining[(key & 0xF) as usize] = self.hold_frames;
+    }
+
+    // For terminals that do report releases
+    pub fn release(&mut self, cpu: &mut Cpu, key: u8) {
+        cpu.key_released(key);
+        self.remaining[(key & 0xF) as usize] = 0;
+    }
+
+    // Count down once per frame, releasing the keys that ran out
+    pub fn tick(&mut self, cpu: &mut Cpu) {
+        for (key, remaining) in self.remaining.iter_mut().enumerate() {
+            if *remaining > 0 {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    cpu.key_released(key as u8);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use super::{assemble, assemble_with_layout, AsmError, Instruction};
+use crate::emulation::MemoryLayout;
+
+fn error(line: usize, column: usize, message: &str) -> AsmError {
+    AsmError {
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+// Labels resolve forwards and backwards, comments are ignored
+#[test]
+fn asm_labels() {
+    let source = "
+        start:  LD I, sprite    ; point at the sprite
+                CALL draw
+        loop:   JP loop
+        draw:   DRW V0, V1, 1
+                RET
+        sprite: db 0xFF
+    ";
+    assert_eq!(
+        assemble(source),
+        Ok(vec![
+            0xA2, 0x0A, 0x22, 0x06, 0x12, 0x04, 0xD0, 0x11, 0x00, 0xEE, 0xFF
+        ])
+    );
+}
+
+// Constants, expressions and number formats
+#[test]
+fn asm_constants() {
+    let source = "
+        speed = 3
+        twice = speed + speed
+        LD V0, twice
+        ADD V1, -1
+        LD V2, $1F
+        LD V3, 0b101
+        LD I, table + 2 - 1
+        table:
+    ";
+    assert_eq!(
+        assemble(source),
+        Ok(vec![
+            0x60, 0x06, 0x71, 0xFF, 0x62, 0x1F, 0x63, 0x05, 0xA2, 0x0B
+        ])
+    );
+}
+
+// db, dw and sprite literals
+#[test]
+fn asm_data() {
+    let source = "
+        db 1, 0x02, -1
+        dw 0x1234, here
+        here:
+        sprite \"X......X\", \"##..##..........\"
+    ";
+    assert_eq!(
+        assemble(source),
+        Ok(vec![
+            0x01, 0x02, 0xFF, 0x12, 0x34, 0x02, 0x07, 0x81, 0xCC, 0x00
+        ])
+    );
+}
+
+// Every mnemonic chip8-disasm prints assembles back to its opcode
+#[test]
+fn asm_all_instructions() {
+    for opcode in 0..=0xFFFF {
+        match Instruction::decode(opcode) {
+            Ok(Instruction::LoadILong) | Err(_) => {}
+            Ok(instruction) => {
+                let source = instruction.to_string();
+                assert_eq!(
+                    assemble(&source),
+                    Ok(opcode.to_be_bytes().to_vec()),
+                    "{}",
+                    source
+                );
+            }
+        }
+    }
+    assert_eq!(
+        assemble("LD I, LONG 0x1234"),
+        Ok(vec![0xF0, 0x00, 0x12, 0x34])
+    );
+    assert_eq!(assemble("shr v1"), Ok(vec![0x81, 0x16]));
+}
+
+// Errors point at line and column
+#[test]
+fn asm_errors() {
+    assert_eq!(
+        assemble("CLS\n  FOO V0"),
+        Err(error(2, 3, "unknown mnemonic 'FOO'"))
+    );
+    assert_eq!(
+        assemble("LD V0, 0x100"),
+        Err(error(1, 8, "byte 256 out of range"))
+    );
+    assert_eq!(
+        assemble("JP nowhere"),
+        Err(error(1, 4, "unknown symbol 'nowhere'"))
+    );
+    assert_eq!(
+        assemble("DRW V0, V1"),
+        Err(error(1, 1, "invalid operands for DRW"))
+    );
+    assert_eq!(
+        assemble("a: CLS\na: CLS"),
+        Err(error(2, 1, "'a' is already defined"))
+    );
+    assert_eq!(
+        assemble("sprite \"XX\""),
+        Err(error(1, 8, "sprite rows are 8 or 16 pixels wide"))
+    );
+    assert_eq!(
+        assemble("LD V0, @"),
+        Err(error(1, 8, "unexpected character '@'"))
+    );
+}
+
+// Labels resolve from the program start of the layout
+#[test]
+fn asm_layout_program_start() {
+    let source = "
+        start:  LD I, sprite
+        loop:   JP loop
+        sprite: db 0xFF
+    ";
+    assert_eq!(
+        assemble_with_layout(source, MemoryLayout::ETI_660),
+        Ok(vec![0xA6, 0x04, 0x16, 0x02, 0xFF])
+    );
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use super::RomBrowser;
+
+// The roms directory lists the programs with their descriptions
+#[test]
+fn browser_scan() {
+    let browser = RomBrowser::scan(Path::new("roms")).unwrap();
+    let names: Vec<&str> = browser.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Chip8_Logo",
+            "dummy_code",
+            "IBM_Logo",
+            "Maze_[David Winter, 199x]",
+            "Tetris_[Fran_Dachille,1991]"
+        ]
+    );
+    assert!(browser.entries[0].description.is_none());
+    assert!(browser.entries[3].description.is_some());
+}
+
+#[test]
+fn browser_selection() {
+    let mut browser = RomBrowser::scan(Path::new("roms")).unwrap();
+    assert_eq!(browser.selected().unwrap().name, "Chip8_Logo");
+
+    browser.select_previous();
+    assert_eq!(browser.selected, 4);
+    browser.select_next();
+    assert_eq!(browser.selected, 0);
+
+    browser.select_path(Path::new("roms/IBM_Logo.ch8"));
+    assert_eq!(browser.selected().unwrap().name, "IBM_Logo");
+}
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+
+use super::{Cartridge, CartridgeError};
+use crate::emulation::{Platform, Quirks};
+
+// Hide a payload in a GIF the way Octo does, behind a label of palette index 0
+fn build_cartridge(payload: &str) -> Vec<u8> {
+    let (width, height) = (32, 16);
+    let mut data = (payload.len() as u32).to_be_bytes().to_vec();
+    data.extend(payload.as_bytes());
+
+    let mut pixels = Vec::new();
+    for byte in data {
+        for shift in [6, 4, 2, 0] {
+            // The high bits carry the label image and are ignored
+            pixels.push(0x4 | (byte >> shift & 3));
+        }
+    }
+    let frame_size = width as usize * height as usize;
+    pixels.resize(pixels.len().div_ceil(frame_size) * frame_size, 0x4);
+
+    let palette = [0u8; 8 * 3];
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, width, height, &palette).unwrap();
+        for chunk in pixels.chunks(frame_size) {
+            let frame = gif::Frame {
+                width,
+                height,
+                buffer: Cow::Borrowed(chunk),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+    }
+    gif
+}
+
+// Source and options come out of the pixels, spread over several frames
+#[test]
+fn cartridge_decode() {
+    let source = format!(": main\n{}  loop again\n", "  v0 += 1\n".repeat(40));
+    let payload = serde_json::json!({
+        "program": source,
+        "options": {
+            "tickrate": 200,
+            "maxSize": 65024,
+            "shiftQuirks": false,
+            "loadStoreQuirks": false,
+            "clipQuirks": false,
+            "backgroundColor": "#996600",
+            "fillColor": "#FFCC00",
+            "fillColor2": "#FF6600",
+            "blendColor": "#662200",
+        }
+    });
+    let gif = build_cartridge(&payload.to_string());
+
+    let cartridge = Cartridge::decode(&gif).unwrap();
+    assert_eq!(cartridge.source, source);
+    assert_eq!(cartridge.rom.len(), 40 * 2 + 2);
+    assert_eq!(cartridge.rom[..2], [0x70, 0x01]);
+    assert_eq!(cartridge.rom[80..], [0x12, 0x50]);
+    assert_eq!(cartridge.platform, Platform::XoChip);
+    assert_eq!(cartridge.quirks, Quirks::MODERN);
+    assert_eq!(cartridge.instructions_per_frame, 200);
+    assert_eq!(
+        cartridge.colours,
+        Some([0x996600, 0xFFCC00, 0xFF6600, 0x662200])
+    );
+}
+
+// SCHIP cartridges have the SCHIP quirks set
+#[test]
+fn cartridge_decode_schip() {
+    let payload = serde_json::json!({
+        "program": ": main hires loop again",
+        "options": {
+            "maxSize": 3583,
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "jumpQuirks": true,
+            "clipQuirks": true,
+        }
+    });
+    let cartridge = Cartridge::decode(&build_cartridge(&payload.to_string())).unwrap();
+    assert_eq!(cartridge.platform, Platform::SuperChip);
+    assert_eq!(cartridge.quirks, Quirks::SCHIP);
+    assert_eq!(cartridge.colours, None);
+    assert_eq!(cartridge.rom, [0x00, 0xFF, 0x12, 0x02]);
+}
+
+#[test]
+fn cartridge_decode_errors() {
+    assert!(matches!(
+        Cartridge::decode(b"not a gif"),
+        Err(CartridgeError::Gif(_))
+    ));
+    assert!(matches!(
+        Cartridge::decode(&build_cartridge("{}")),
+        Err(CartridgeError::Payload(_))
+    ));
+    let payload = serde_json::json!({ "program": ": main jump nowhere" });
+    assert!(matches!(
+        Cartridge::decode(&build_cartridge(&payload.to_string())),
+        Err(CartridgeError::Compile(_))
+    ));
+}
@@ -1,10 +1,17 @@
-#[cfg(test)]
+#![allow(
+    non_snake_case,
+    non_upper_case_globals,
+    clippy::identity_op,
+    clippy::module_inception
+)]
 
+#[cfg(test)]
 const Rom_Dummy: [u8; 3] = [0x42; 3];
 
 mod tests {
     use super::*;
-    use crate::emulation::{Cpu, Disk};
+    use crate::emulation::{Cpu, CpuError, Disk, DiskError, MemoryLayout, Platform, Quirks};
+    use rand::rngs::mock::StepRng;
 
     // Cpu instantiation
     #[test]
@@ -21,7 +28,7 @@ mod tests {
         let disk = disk_load_stub(&Rom_Dummy);
         let mut cpu = Cpu::new();
 
-        cpu.load_disk_to_ram(&disk);
+        cpu.load_disk_to_ram(&disk).unwrap();
 
         assert_eq!(cpu.ram[0], 0xF0); // 0xF0 is the value of the first font character
 
@@ -30,11 +37,188 @@ mod tests {
         }
     }
 
+    // Programs larger than ram behind 0x200 are refused
+    #[test]
+    fn cpu_load_disk_to_ram_too_large() {
+        let disk = disk_load_stub(&[0x42; 0x1000 - 0x200 + 1]);
+        let mut cpu = Cpu::new();
+        assert!(matches!(
+            cpu.load_disk_to_ram(&disk),
+            Err(DiskError::TooLarge {
+                size: 0xE01,
+                max: 0xE00
+            })
+        ));
+
+        // The same program fits into XO-CHIP memory
+        let mut cpu = Cpu::with_platform(Platform::XoChip, Quirks::MODERN);
+        cpu.load_disk_to_ram(&disk).unwrap();
+        assert_eq!(cpu.ram[0x1000], 0x42);
+    }
+
     // Next Cpu tick
     #[test]
-    fn cpu_next() {
-        assert_eq!(1, 1);
-        //TODO: Add tests after next is completed
+    fn cpu_step() {
+        let mut cpu = Cpu::new();
+        cpu.ram[0x200] = 0x61;
+        cpu.ram[0x201] = 0x42;
+        cpu.reg_delay_timer = 2;
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_v[1], 0x42);
+        assert_eq!(cpu.reg_pc, 0x202);
+        assert_eq!(cpu.reg_delay_timer, 2);
+    }
+
+    // Reset starts over with the same settings
+    #[test]
+    fn cpu_reset() {
+        let mut cpu = Cpu::with_platform(Platform::SuperChip, Quirks::SCHIP);
+        cpu.load_disk_to_ram(&disk_load_stub(&[0x61, 0x42, 0x00, 0xFF]))
+            .unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        cpu.framebuffer.set_pixel(0, 0, 1);
+
+        cpu.reset();
+        assert_eq!(cpu.reg_v[1], 0);
+        assert_eq!(cpu.reg_pc, 0x200);
+        assert_eq!(cpu.ram[0x200], 0);
+        assert_eq!(cpu.ram[0], 0xF0);
+        assert!(!cpu.hires());
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 0);
+        assert_eq!(cpu.platform, Platform::SuperChip);
+        assert_eq!(cpu.quirks, Quirks::SCHIP);
+    }
+
+    // Timers count down to zero
+    #[test]
+    fn cpu_tick_timers() {
+        let mut cpu = Cpu::new();
+        cpu.reg_delay_timer = 1;
+        cpu.reg_sound_timer = 2;
+
+        cpu.tick_timers();
+        assert_eq!(cpu.reg_delay_timer, 0);
+        assert_eq!(cpu.reg_sound_timer, 1);
+
+        cpu.tick_timers();
+        assert_eq!(cpu.reg_delay_timer, 0);
+        assert_eq!(cpu.reg_sound_timer, 0);
+    }
+
+    // Unknown opcodes report their address
+    #[test]
+    fn cpu_step_unknown_opcode() {
+        let mut cpu = Cpu::new();
+        cpu.ram[0x200] = 0x81;
+        cpu.ram[0x201] = 0x2F;
+
+        assert_eq!(
+            cpu.step(),
+            Err(CpuError::UnknownOpcode {
+                opcode: 0x812F,
+                address: 0x200
+            })
+        );
+    }
+
+    // Program counter past the end of ram
+    #[test]
+    fn cpu_step_pc_out_of_range() {
+        let mut cpu = Cpu::new();
+        cpu.reg_pc = 0x1000;
+        assert_eq!(cpu.step(), Err(CpuError::PcOutOfRange { pc: 0x1000 }));
+    }
+
+    // Stack overflow and underflow
+    #[test]
+    fn cpu_stack_errors() {
+        let mut cpu = get_cpu_with_opcode(0x00EE);
+        assert!(matches!(
+            cpu.execute(),
+            Err(CpuError::StackUnderflow { .. })
+        ));
+
+        let mut cpu = get_cpu_with_opcode(0x2200);
+        for _ in 0..16 {
+            cpu.execute().unwrap();
+        }
+        assert!(matches!(cpu.execute(), Err(CpuError::StackOverflow { .. })));
+    }
+
+    // Memory access past the end of ram
+    #[test]
+    fn cpu_memory_fault() {
+        let mut cpu = get_cpu_with_opcode(0xF255);
+        cpu.reg_i = 0xFFE;
+        assert!(matches!(
+            cpu.execute(),
+            Err(CpuError::MemoryFault {
+                address: 0x1000,
+                ..
+            })
+        ));
+    }
+
+    // Programs start where the memory layout says
+    #[test]
+    fn cpu_memory_layout_program_start() {
+        let mut cpu =
+            Cpu::with_layout(Platform::Chip8, Quirks::default(), MemoryLayout::ETI_660).unwrap();
+        assert_eq!(cpu.reg_pc, 0x600);
+
+        cpu.load_disk_to_ram(&disk_load_stub(&[0x60, 0x42]))
+            .unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_v[0], 0x42);
+
+        // Only 0x1000 - 0x600 bytes fit behind 0x600
+        let disk = disk_load_stub(&[0; 0xA01]);
+        assert!(matches!(
+            cpu.load_disk_to_ram(&disk),
+            Err(DiskError::TooLarge { max: 0xA00, .. })
+        ));
+    }
+
+    // Fx29 and Fx30 point into the fonts wherever they are
+    #[test]
+    fn cpu_memory_layout_fonts() {
+        let layout = MemoryLayout {
+            font_address: 0x050,
+            big_font_address: 0x0A0,
+            ..MemoryLayout::for_platform(Platform::SuperChip)
+        };
+        let mut cpu = Cpu::with_layout(Platform::SuperChip, Quirks::SCHIP, layout).unwrap();
+        assert_eq!(cpu.ram[0x050], 0xF0);
+
+        cpu.reg_v[1] = 2;
+        cpu.opcode = 0xF129;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x050 + 10);
+
+        cpu.opcode = 0xF130;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x0A0 + 20);
+    }
+
+    // Writes to the reserved interpreter area fault
+    #[test]
+    fn cpu_memory_layout_reserved() {
+        let layout = MemoryLayout {
+            reserved: 0x200,
+            ..MemoryLayout::for_platform(Platform::Chip8)
+        };
+        let mut cpu = Cpu::with_layout(Platform::Chip8, Quirks::default(), layout).unwrap();
+        cpu.opcode = 0xF033;
+        cpu.reg_i = 0x1FF;
+        assert!(matches!(
+            cpu.execute(),
+            Err(CpuError::MemoryFault { address: 0x1FF, .. })
+        ));
+
+        cpu.reg_i = 0x200;
+        cpu.execute().unwrap();
     }
 
     // Test Opcode 0x00E0
@@ -43,13 +227,13 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0x00E0);
 
         // Dirty up the video ram
-        assert_eq!(cpu.video_ram[0][0], 0);
-        cpu.video_ram[0][0] = 1;
-        assert_eq!(cpu.video_ram[0][0], 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 0);
+        cpu.framebuffer.set_pixel(0, 0, 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 1);
 
         // Execute the opcode
-        cpu.execute();
-        assert_eq!(cpu.video_ram[0][0], 0);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 0);
     }
 
     // Test Opcode 0x00EE
@@ -62,8 +246,9 @@ mod tests {
         cpu.reg_sp = 1;
 
         // Execute the opcode
-        cpu.execute();
-        assert_eq!(cpu.stack_ptr, 0);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_sp, 0);
+        assert_eq!(cpu.reg_pc, 1);
     }
 
     // Test Opcode 0x1NNN
@@ -72,7 +257,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0x1123);
 
         // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x123);
     }
 
@@ -82,7 +267,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0x2123);
 
         // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x123);
     }
 
@@ -93,12 +278,12 @@ mod tests {
 
         // False case
         cpu.reg_v[1] = 0;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 0);
 
         // True case
         cpu.reg_v[1] = 1;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 2);
     }
 
@@ -109,12 +294,12 @@ mod tests {
 
         // False case
         cpu.reg_v[1] = 1;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 0);
 
         // True case
         cpu.reg_v[1] = 0;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 2);
     }
 
@@ -126,12 +311,12 @@ mod tests {
         // False case
         cpu.reg_v[1] = 0;
         cpu.reg_v[2] = 1;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 0);
 
         // True case
         cpu.reg_v[1] = 1;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x200 + 2);
     }
 
@@ -141,7 +326,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0x6101);
 
         // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x01);
     }
 
@@ -152,7 +337,7 @@ mod tests {
         cpu.reg_v[1] = 1;
 
         // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x02);
     }
 
@@ -164,7 +349,7 @@ mod tests {
         cpu.reg_v[2] = 2; // y
 
         // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 2);
     }
 
@@ -178,7 +363,7 @@ mod tests {
                           // 1 0
                           // 1 1
                           // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 3);
     }
 
@@ -192,7 +377,7 @@ mod tests {
                           // 1 1
                           // 0 1
                           // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 1);
     }
 
@@ -206,7 +391,7 @@ mod tests {
                           // 1 1
                           // 1 0
                           // Execute the opcode
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 2);
     }
 
@@ -218,14 +403,14 @@ mod tests {
         // No overflow
         cpu.reg_v[1] = 1; // vx
         cpu.reg_v[2] = 3; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 4);
         assert_eq!(cpu.reg_v[15], 0);
 
         // Overflow
         cpu.reg_v[1] = 0xFF; // vx
         cpu.reg_v[2] = 1; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0);
         assert_eq!(cpu.reg_v[15], 1);
     }
@@ -238,14 +423,14 @@ mod tests {
         // No borrow
         cpu.reg_v[1] = 1; // vx
         cpu.reg_v[2] = 2; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0xFF);
         assert_eq!(cpu.reg_v[15], 0);
 
         // Borrow
         cpu.reg_v[1] = 2; // vx
         cpu.reg_v[2] = 1; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 1);
         assert_eq!(cpu.reg_v[15], 1);
     }
@@ -257,13 +442,13 @@ mod tests {
 
         // Even LSB
         cpu.reg_v[1] = 4; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 2);
         assert_eq!(cpu.reg_v[15], 0);
 
         // Uneven LSB
         cpu.reg_v[1] = 5; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 2);
         assert_eq!(cpu.reg_v[15], 1);
     }
@@ -275,13 +460,13 @@ mod tests {
         // Vx = Vy - Vx
         cpu.reg_v[1] = 1; // vx
         cpu.reg_v[2] = 0x0F; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x0E);
         assert_eq!(cpu.reg_v[15], 1);
 
         cpu.reg_v[1] = 0xFF; // vx
         cpu.reg_v[2] = 0x0F; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x10);
         assert_eq!(cpu.reg_v[15], 0);
     }
@@ -292,7 +477,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0x812E);
 
         cpu.reg_v[1] = 0xC0; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x80);
         assert_eq!(cpu.reg_v[15], 1);
     }
@@ -306,14 +491,14 @@ mod tests {
         cpu.reg_pc = 0;
         cpu.reg_v[1] = 1; // vx
         cpu.reg_v[2] = 2; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 2);
 
         // Don't jump
         cpu.reg_pc = 0;
         cpu.reg_v[1] = 1; // vx
         cpu.reg_v[2] = 1; // vy
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0);
     }
 
@@ -321,7 +506,7 @@ mod tests {
     #[test]
     fn cpu_0xAnnn() {
         let mut cpu = get_cpu_with_opcode(0xA123);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_i, 0x123);
     }
 
@@ -330,16 +515,37 @@ mod tests {
     fn cpu_0xBnnn() {
         let mut cpu = get_cpu_with_opcode(0xB123);
         cpu.reg_v[0] = 1; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0x124);
     }
 
     // Test Opcode 0xCXNN
     #[test]
     fn cpu_0xCxnn() {
+        // A constant generator makes the result exact, 0xFF included
         let mut cpu = get_cpu_with_opcode(0xC1FF);
-        cpu.execute();
-        println!("Random Value {:X}", cpu.reg_v[1]);
+        cpu.rng = Box::new(StepRng::new(0xFF, 0));
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[1], 0xFF);
+
+        let mut cpu = get_cpu_with_opcode(0xC10F);
+        cpu.rng = Box::new(StepRng::new(0xAB, 0));
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[1], 0x0B);
+    }
+
+    // Same seed, same random sequence
+    #[test]
+    fn cpu_0xCxnn_seeded() {
+        let mut first = get_cpu_with_opcode(0xC1FF);
+        let mut second = get_cpu_with_opcode(0xC1FF);
+        first.seed_rng(42);
+        second.seed_rng(42);
+        for _ in 0..16 {
+            first.execute().unwrap();
+            second.execute().unwrap();
+            assert_eq!(first.reg_v[1], second.reg_v[1]);
+        }
     }
 
     // Test Opcode 0xDXYN
@@ -357,13 +563,13 @@ mod tests {
 
         // Pressed
         cpu.reg_pc = 0;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 2);
 
         // Not pressed
         cpu.reg_pc = 0;
         cpu.keyboard[1] = false;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0);
     }
 
@@ -376,13 +582,13 @@ mod tests {
 
         // Pressed
         cpu.reg_pc = 0;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0);
 
         // Not pressed
         cpu.reg_pc = 0;
         cpu.keyboard[1] = false;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 2);
     }
 
@@ -391,7 +597,7 @@ mod tests {
     fn cpu_0xFx07() {
         let mut cpu = get_cpu_with_opcode(0xF107);
         cpu.reg_delay_timer = 0x20;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[1], 0x20);
     }
 
@@ -404,14 +610,30 @@ mod tests {
         cpu.reg_pc = 2;
         cpu.reg_v[1] = 1; // vx
         cpu.keyboard[1] = false; // key
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 0);
 
         // Pressed -> PC == 2
         cpu.keyboard[1] = true; // key
         cpu.reg_pc = 2;
-        cpu.execute();
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_pc, 2);
+    }
+
+    // Keys are set and cleared by their keypad number, key 0 included
+    #[test]
+    fn cpu_key_pressed_released() {
+        let mut cpu = get_cpu_with_opcode(0xF20A);
+        cpu.key_pressed(0x0);
+        cpu.reg_pc = 2;
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_pc, 2);
+        assert_eq!(cpu.reg_v[2], 0x0);
+
+        cpu.key_released(0x0);
+        cpu.key_pressed(0xF);
+        assert!(!cpu.keyboard[0x0]);
+        assert!(cpu.keyboard[0xF]);
     }
 
     // Test Opcode 0xFX15
@@ -420,7 +642,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0xF115);
         cpu.reg_delay_timer = 0;
         cpu.reg_v[1] = 0x20; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_delay_timer, 0x20);
     }
 
@@ -430,7 +652,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0xF118);
         cpu.reg_sound_timer = 0;
         cpu.reg_v[1] = 0x20; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_sound_timer, 0x20);
     }
 
@@ -440,7 +662,7 @@ mod tests {
         let mut cpu = get_cpu_with_opcode(0xF11E);
         cpu.reg_i = 0x123;
         cpu.reg_v[1] = 0x20; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_i, 0x123 + 0x20);
     }
 
@@ -449,7 +671,7 @@ mod tests {
     fn cpu_0xFx29() {
         let mut cpu = get_cpu_with_opcode(0xF129);
         cpu.reg_v[1] = 1; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_i, 5);
     }
 
@@ -458,7 +680,7 @@ mod tests {
     fn cpu_0xFx33() {
         let mut cpu = get_cpu_with_opcode(0xF133);
         cpu.reg_v[1] = 123; // vx
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.ram[cpu.reg_i as usize], 1);
         assert_eq!(cpu.ram[cpu.reg_i as usize + 1], 2);
         assert_eq!(cpu.ram[cpu.reg_i as usize + 2], 3);
@@ -471,7 +693,7 @@ mod tests {
         cpu.reg_v[0] = 0x12; // vx
         cpu.reg_v[1] = 0x34; // vy
         cpu.reg_v[2] = 0x56; // vz
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.ram[cpu.reg_i as usize], 0x12);
         assert_eq!(cpu.ram[cpu.reg_i as usize + 1], 0x34);
         assert_eq!(cpu.ram[cpu.reg_i as usize + 2], 0x56);
@@ -487,12 +709,337 @@ mod tests {
         cpu.ram[cpu.reg_i as usize] = 0x12;
         cpu.ram[cpu.reg_i as usize + 1] = 0x34;
         cpu.ram[cpu.reg_i as usize + 2] = 0x56;
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.reg_v[0], 0x12);
         assert_eq!(cpu.reg_v[1], 0x34);
         assert_eq!(cpu.reg_v[2], 0x56);
     }
 
+    ////////////////////////////////////////////////////////////////////////////////
+    // QUIRKS
+    ////////////////////////////////////////////////////////////////////////////////
+
+    // 8XY6 and 8XYE shift Vy into Vx
+    #[test]
+    fn quirk_shift_uses_vy() {
+        let mut cpu = get_cpu_with_opcode(0x8126);
+        cpu.quirks.shift_uses_vy = true;
+        cpu.reg_v[1] = 0xFF; // vx
+        cpu.reg_v[2] = 0x05; // vy
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[1], 0x02);
+        assert_eq!(cpu.reg_v[15], 1);
+
+        cpu.opcode = 0x812E;
+        cpu.reg_v[2] = 0x81; // vy
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[1], 0x02);
+        assert_eq!(cpu.reg_v[15], 1);
+    }
+
+    // FX55 and FX65 increment I
+    #[test]
+    fn quirk_load_store_increments_i() {
+        let mut cpu = get_cpu_with_opcode(0xF255);
+        cpu.quirks.load_store_increments_i = true;
+        cpu.reg_i = 0x300;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x303);
+
+        cpu.opcode = 0xF165;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x305);
+    }
+
+    // CHIP-48 leaves I on the last register, SCHIP doesn't move it
+    #[test]
+    fn quirk_load_store_presets() {
+        assert_ne!(Quirks::CHIP48, Quirks::SCHIP);
+
+        let mut cpu = get_cpu_with_opcode(0xF255);
+        cpu.quirks = Quirks::CHIP48;
+        cpu.reg_i = 0x300;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x302);
+
+        cpu.opcode = 0xF165;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x303);
+
+        cpu.quirks = Quirks::SCHIP;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x303);
+    }
+
+    // BXNN jumps relative to Vx
+    #[test]
+    fn quirk_jump_uses_vx() {
+        let mut cpu = get_cpu_with_opcode(0xB123);
+        cpu.quirks.jump_uses_vx = true;
+        cpu.reg_v[0] = 1;
+        cpu.reg_v[1] = 2;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_pc, 0x125);
+    }
+
+    // 8XY1, 8XY2 and 8XY3 reset VF
+    #[test]
+    fn quirk_vf_reset() {
+        for opcode in [0x8121, 0x8122, 0x8123] {
+            let mut cpu = get_cpu_with_opcode(opcode);
+            cpu.quirks.vf_reset = true;
+            cpu.reg_v[15] = 1;
+            cpu.execute().unwrap();
+            assert_eq!(cpu.reg_v[15], 0);
+        }
+    }
+
+    // Sprites at the right edge are clipped or wrapped
+    #[test]
+    fn quirk_clip_sprites() {
+        let mut cpu = get_cpu_with_opcode(0xD011);
+        cpu.reg_i = 0x300;
+        cpu.ram[0x300] = 0xFF;
+        cpu.reg_v[0] = 60;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(63, 0), 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 1);
+
+        let mut cpu = get_cpu_with_opcode(0xD011);
+        cpu.quirks.clip_sprites = true;
+        cpu.reg_i = 0x300;
+        cpu.ram[0x300] = 0xFF;
+        cpu.reg_v[0] = 60;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(63, 0), 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 0);
+    }
+
+    // DXYN waits for the next vblank
+    #[test]
+    fn quirk_display_wait() {
+        let mut cpu = Cpu::with_quirks(Quirks::COSMAC_VIP);
+        // DXY1, DXY1
+        cpu.ram[0x200..0x204].copy_from_slice(&[0xD0, 0x01, 0xD0, 0x01]);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_pc, 0x202);
+
+        cpu.vblank();
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_pc, 0x204);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // SCHIP
+    ////////////////////////////////////////////////////////////////////////////////
+
+    // Test Opcodes 0x00FE and 0x00FF
+    #[test]
+    fn schip_0x00FF() {
+        let mut cpu = get_schip_cpu_with_opcode(0x00FF);
+        cpu.execute().unwrap();
+        assert!(cpu.hires());
+        assert_eq!(cpu.screen_width(), 128);
+        assert_eq!(cpu.screen_height(), 64);
+
+        cpu.opcode = 0x00FE;
+        cpu.execute().unwrap();
+        assert!(!cpu.hires());
+        assert_eq!(cpu.screen_width(), 64);
+        assert_eq!(cpu.screen_height(), 32);
+    }
+
+    // Test Opcode 0x00CN
+    #[test]
+    fn schip_0x00CN() {
+        let mut cpu = get_schip_cpu_with_opcode(0x00C3);
+        cpu.framebuffer.set_pixel(5, 0, 1);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(5, 0), 0);
+        assert_eq!(cpu.framebuffer.pixel(5, 3), 1);
+    }
+
+    // Test Opcodes 0x00FB and 0x00FC
+    #[test]
+    fn schip_0x00FB_0x00FC() {
+        let mut cpu = get_schip_cpu_with_opcode(0x00FB);
+        cpu.framebuffer.set_pixel(62, 1, 1);
+        cpu.framebuffer.set_pixel(2, 1, 1);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(6, 1), 1);
+        assert_eq!(cpu.framebuffer.pixel(2, 1), 0);
+        assert_eq!(cpu.framebuffer.pixel(0, 1), 0);
+
+        cpu.opcode = 0x00FC;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(2, 1), 1);
+        assert_eq!(cpu.framebuffer.pixel(63, 1), 0);
+    }
+
+    // Test Opcode 0x00FD
+    #[test]
+    fn schip_0x00FD() {
+        let mut cpu = Cpu::with_platform(Platform::SuperChip, Quirks::SCHIP);
+        cpu.ram[0x200..0x204].copy_from_slice(&[0x00, 0xFD, 0x60, 0x01]);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(cpu.exited);
+        assert_eq!(cpu.reg_pc, 0x202);
+        assert_eq!(cpu.reg_v[0], 0);
+    }
+
+    // Test Opcode 0xDXY0
+    #[test]
+    fn schip_0xDxy0() {
+        let mut cpu = get_schip_cpu_with_opcode(0xD010);
+        cpu.reg_i = 0x300;
+        cpu.ram[0x300] = 0x80;
+        cpu.ram[0x301] = 0x01;
+        cpu.ram[0x31E] = 0xFF;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 1);
+        assert_eq!(cpu.framebuffer.pixel(15, 0), 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 15), 1);
+        assert_eq!(cpu.framebuffer.pixel(8, 15), 0);
+        assert_eq!(cpu.reg_v[15], 0);
+
+        // Drawing again collides
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 0);
+        assert_eq!(cpu.reg_v[15], 1);
+    }
+
+    // Test Opcode 0xFX30
+    #[test]
+    fn schip_0xFx30() {
+        let mut cpu = get_schip_cpu_with_opcode(0xF130);
+        cpu.reg_v[1] = 2;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_i, 0x50 + 20);
+        assert_eq!(cpu.ram[cpu.reg_i as usize], 0x3E);
+    }
+
+    // Test Opcodes 0xFX75 and 0xFX85
+    #[test]
+    fn schip_0xFx75_0xFx85() {
+        let mut cpu = get_schip_cpu_with_opcode(0xF275);
+        cpu.reg_v[0] = 0x12;
+        cpu.reg_v[1] = 0x34;
+        cpu.reg_v[2] = 0x56;
+        cpu.execute().unwrap();
+
+        cpu.reg_v = [0; 16];
+        cpu.opcode = 0xF285;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[..3], [0x12, 0x34, 0x56]);
+    }
+
+    // SCHIP opcodes are rejected on the CHIP-8 platform
+    #[test]
+    fn schip_opcodes_on_chip8() {
+        for opcode in [0xF130, 0xF175] {
+            let mut cpu = get_cpu_with_opcode(opcode);
+            assert!(matches!(cpu.execute(), Err(CpuError::UnknownOpcode { .. })));
+        }
+    }
+
+    // 00xx words stay machine code calls on CHIP-8, which do nothing
+    #[test]
+    fn schip_0x00FF_on_chip8() {
+        let mut cpu = Cpu::new();
+        cpu.ram[0x200..0x206].copy_from_slice(&[0x00, 0xFF, 0x00, 0xC1, 0x00, 0xFD]);
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.reg_pc, 0x206);
+        assert!(!cpu.hires());
+        assert!(!cpu.exited);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // XO-CHIP
+    ////////////////////////////////////////////////////////////////////////////////
+
+    // XO-CHIP has 64 KiB of ram
+    #[test]
+    fn xochip_ram_size() {
+        let cpu = Cpu::with_platform(Platform::XoChip, Quirks::MODERN);
+        assert_eq!(cpu.ram.len(), 0x10000);
+    }
+
+    // Test Opcode 0xF000 0xNNNN
+    #[test]
+    fn xochip_0xF000() {
+        let mut cpu = Cpu::with_platform(Platform::XoChip, Quirks::MODERN);
+        cpu.ram[0x200..0x204].copy_from_slice(&[0xF0, 0x00, 0xE0, 0x12]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_i, 0xE012);
+        assert_eq!(cpu.reg_pc, 0x204);
+    }
+
+    // Skips jump over the whole 4 byte F000 NNNN
+    #[test]
+    fn xochip_skip_long_instruction() {
+        let mut cpu = Cpu::with_platform(Platform::XoChip, Quirks::MODERN);
+        // SE V0, 0; LD I, long 0x1234; LD V1, 1
+        cpu.ram[0x200..0x208].copy_from_slice(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg_pc, 0x206);
+    }
+
+    // Test Opcodes 0x5XY2 and 0x5XY3
+    #[test]
+    fn xochip_0x5xy2_0x5xy3() {
+        let mut cpu = get_xochip_cpu_with_opcode(0x5132);
+        cpu.reg_i = 0x300;
+        cpu.reg_v[1..4].copy_from_slice(&[0x12, 0x34, 0x56]);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.ram[0x300..0x303], [0x12, 0x34, 0x56]);
+        assert_eq!(cpu.reg_i, 0x300);
+
+        // Reversed range loads in reverse order
+        cpu.opcode = 0x5313;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.reg_v[1..4], [0x56, 0x34, 0x12]);
+    }
+
+    // Test Opcode 0xFN01 and drawing to two planes
+    #[test]
+    fn xochip_0xFn01() {
+        let mut cpu = get_xochip_cpu_with_opcode(0xF301);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.planes, 3);
+
+        // One line for the first plane, one for the second
+        cpu.opcode = 0xD011;
+        cpu.reg_i = 0x300;
+        cpu.ram[0x300] = 0x80;
+        cpu.ram[0x301] = 0xC0;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 3);
+        assert_eq!(cpu.framebuffer.pixel(1, 0), 2);
+
+        // Clearing the first plane keeps the second
+        cpu.opcode = 0xF101;
+        cpu.execute().unwrap();
+        cpu.opcode = 0x00E0;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 0), 2);
+        assert_eq!(cpu.framebuffer.pixel(1, 0), 2);
+    }
+
+    // Test Opcode 0x00DN, scrolling the selected plane only
+    #[test]
+    fn xochip_0x00DN() {
+        let mut cpu = get_xochip_cpu_with_opcode(0x00D2);
+        cpu.planes = 2;
+        cpu.framebuffer.set_pixel(0, 4, 3);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.framebuffer.pixel(0, 4), 1);
+        assert_eq!(cpu.framebuffer.pixel(0, 2), 2);
+    }
+
     ////////////////////////////////////////////////////////////////////////////////
     // HELPER FUNCTIONS
     ////////////////////////////////////////////////////////////////////////////////
@@ -501,18 +1048,25 @@ mod tests {
     fn get_cpu_with_opcode(opcode: u16) -> Cpu {
         let mut cpu = Cpu::new();
         cpu.opcode = opcode;
-        return cpu;
+        cpu
+    }
+
+    // SCHIP opcode test helper
+    fn get_schip_cpu_with_opcode(opcode: u16) -> Cpu {
+        let mut cpu = Cpu::with_platform(Platform::SuperChip, Quirks::SCHIP);
+        cpu.opcode = opcode;
+        cpu
+    }
+
+    // XO-CHIP opcode test helper
+    fn get_xochip_cpu_with_opcode(opcode: u16) -> Cpu {
+        let mut cpu = Cpu::with_platform(Platform::XoChip, Quirks::MODERN);
+        cpu.opcode = opcode;
+        cpu
     }
 
     // Disk load stub
     fn disk_load_stub(rom_array: &[u8]) -> Disk {
-        let mut disk = Disk {
-            rom: [0; 4095],
-            size: rom_array.len(),
-        };
-        for i in 0..rom_array.len() {
-            disk.rom[i] = rom_array[i];
-        }
-        return disk;
+        Disk::from_rom(rom_array.to_vec())
     }
 }
@@ -0,0 +1,115 @@
+use super::{disassemble, disassemble_with_layout, Platform};
+use crate::emulation::MemoryLayout;
+
+// Only the mnemonics of a listing, without labels and comments
+fn mnemonics(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .filter(|line| line.starts_with("    "))
+        .map(|line| line.split(';').next().unwrap().trim().to_string())
+        .collect()
+}
+
+// Code reached from the start is decoded, the sprite behind it is data
+#[test]
+fn disasm_separates_code_and_data() {
+    let rom = [
+        0xA2, 0x06, // LD I, sprite
+        0xD0, 0x12, // DRW V0, V1, 2
+        0x12, 0x04, // JP 0x204
+        0xF0, 0x90, // sprite
+    ];
+    let listing = disassemble(&rom, Platform::Chip8);
+    assert_eq!(
+        mnemonics(&listing),
+        ["LD I, D206", "DRW V0, V1, 2", "JP L204", "db 0xF0, 0x90"]
+    );
+    assert!(listing.contains("L204:\n    JP L204"));
+    assert!(listing.contains("D206:\n    db 0xF0, 0x90"));
+}
+
+// Both paths of a skip and the return from a call are followed
+#[test]
+fn disasm_follows_skips_and_calls() {
+    let rom = [
+        0x22, 0x08, // CALL 0x208
+        0x30, 0x01, // SE V0, 0x01
+        0x12, 0x02, // JP 0x202
+        0x00, 0xFD, // EXIT, only reached through the skip
+        0x00, 0xEE, // RET
+        0xFF, 0xFF, // data
+    ];
+    let listing = disassemble(&rom, Platform::SuperChip);
+    assert_eq!(
+        mnemonics(&listing),
+        [
+            "CALL L208",
+            "SE V0, 0x01",
+            "JP L202",
+            "EXIT",
+            "RET",
+            "db 0xFF, 0xFF"
+        ]
+    );
+}
+
+// Bnnn targets are followed, the unreachable word behind the jump stays data
+#[test]
+fn disasm_follows_jump_offset() {
+    let rom = [
+        0xB2, 0x04, // JP V0, 0x204
+        0x60, 0x01, // unreachable
+        0x12, 0x04, // JP 0x204
+    ];
+    let listing = disassemble(&rom, Platform::Chip8);
+    assert_eq!(
+        mnemonics(&listing),
+        ["JP V0, L204", "db 0x60, 0x01", "JP L204"]
+    );
+}
+
+// Instructions of a newer platform end the traced code, 00xx words are machine code calls
+#[test]
+fn disasm_respects_platform() {
+    let rom = [0x00, 0xFF, 0x12, 0x00];
+    assert_eq!(
+        mnemonics(&disassemble(&rom, Platform::Chip8)),
+        ["SYS 0x0FF", "JP L200"]
+    );
+    assert_eq!(
+        mnemonics(&disassemble(&rom, Platform::SuperChip)),
+        ["HIGH", "JP L200"]
+    );
+
+    let rom = [0xF1, 0x30, 0x12, 0x00];
+    assert_eq!(
+        mnemonics(&disassemble(&rom, Platform::Chip8)),
+        ["db 0xF1, 0x30, 0x12, 0x00"]
+    );
+}
+
+// F000 NNNN is listed as one instruction with its address
+#[test]
+fn disasm_long_load() {
+    let rom = [0xF0, 0x00, 0x02, 0x06, 0x12, 0x04, 0xAA];
+    assert_eq!(
+        mnemonics(&disassemble(&rom, Platform::XoChip)),
+        ["LD I, LONG D206", "JP L204", "db 0xAA"]
+    );
+}
+
+// Code is traced from the program start of the layout
+#[test]
+fn disasm_layout_program_start() {
+    let rom = [
+        0xA6, 0x04, // LD I, sprite
+        0x16, 0x02, // JP 0x602
+        0xF0, 0x90, // sprite
+    ];
+    let listing = disassemble_with_layout(&rom, Platform::Chip8, MemoryLayout::ETI_660);
+    assert_eq!(
+        mnemonics(&listing),
+        ["LD I, D604", "JP L602", "db 0xF0, 0x90"]
+    );
+    assert!(listing.contains("L602:\n    JP L602"));
+}
@@ -0,0 +1,127 @@
+use std::{env, fs, path::PathBuf};
+
+use super::{Disk, DiskError, MemoryLayout, Platform};
+
+// Write a ROM to a file that is unique per test
+fn write_rom(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!("chip8-disk-{}-{}.ch8", name, std::process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+// The whole file is read, not just the first 4 KiB
+#[test]
+fn disk_load() {
+    let bytes: Vec<u8> = (0..0x2000).map(|i| i as u8).collect();
+    let path = write_rom("load", &bytes);
+    let disk = Disk::load(path.to_str().unwrap(), Platform::XoChip).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(disk.size, 0x2000);
+    assert_eq!(disk.rom, bytes);
+}
+
+// Known ROMs come with their database entry and .txt description
+#[test]
+fn disk_load_info() {
+    let disk = Disk::load("roms/Tetris_[Fran_Dachille,1991].ch8", Platform::Chip8).unwrap();
+    assert_eq!(disk.info.unwrap().title, "Tetris");
+    assert!(disk.description.unwrap().contains("Fran Dachille"));
+
+    let disk = Disk::load("roms/dummy_code.ch8", Platform::Chip8).unwrap();
+    assert!(disk.info.is_none());
+    assert!(disk.description.is_none());
+}
+
+// Exported hex reloads byte for byte, in both formats
+#[test]
+fn disk_hex_round_trip() {
+    let tetris = Disk::load("roms/Tetris_[Fran_Dachille,1991].ch8", Platform::Chip8).unwrap();
+
+    let plain = Disk::from_hex(&tetris.to_hex()).unwrap();
+    assert_eq!(plain.rom, tetris.rom);
+    assert_eq!(plain.info, tetris.info);
+
+    let path = env::temp_dir().join(format!("chip8-disk-ihex-{}.hex", std::process::id()));
+    fs::write(
+        &path,
+        tetris.to_intel_hex(MemoryLayout::for_platform(Platform::Chip8)),
+    )
+    .unwrap();
+    let intel = Disk::load(path.to_str().unwrap(), Platform::Chip8).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(intel.rom, tetris.rom);
+}
+
+// The old constructor still reads whole files
+#[test]
+#[allow(deprecated)]
+fn disk_new() {
+    let disk = Disk::new("roms/dummy_code.ch8");
+    assert_eq!(disk.size, disk.rom.len());
+    assert!(disk.size > 0);
+}
+
+#[test]
+fn disk_load_not_found() {
+    let result = Disk::load("roms/does_not_exist.ch8", Platform::Chip8);
+    assert!(matches!(result, Err(DiskError::NotFound { .. })));
+}
+
+#[test]
+fn disk_load_empty() {
+    let path = write_rom("empty", &[]);
+    let result = Disk::load(path.to_str().unwrap(), Platform::Chip8);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(DiskError::Empty { .. })));
+}
+
+// The limit depends on the memory of the platform
+#[test]
+fn disk_load_too_large() {
+    let path = write_rom("large", &[0x12; 0xE01]);
+    let chip8 = Disk::load(path.to_str().unwrap(), Platform::Chip8);
+    let xochip = Disk::load(path.to_str().unwrap(), Platform::XoChip);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        chip8,
+        Err(DiskError::TooLarge {
+            size: 0xE01,
+            max: 0xE00
+        })
+    ));
+    assert_eq!(xochip.unwrap().size, 0xE01);
+}
+
+// ETI 660 programs start at 0x600, Intel HEX records carry that address
+#[test]
+fn disk_load_eti_660() {
+    let bytes: Vec<u8> = (0..0x20).collect();
+    let path = write_rom("eti660", &bytes);
+    let disk = Disk::load_with_layout(path.to_str().unwrap(), MemoryLayout::ETI_660).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(disk.rom, bytes);
+
+    let text = disk.to_intel_hex(MemoryLayout::ETI_660);
+    assert!(text.starts_with(":10060000"));
+    let reloaded = Disk::from_intel_hex(&text, MemoryLayout::ETI_660).unwrap();
+    assert_eq!(reloaded.rom, bytes);
+
+    // Records below the program start don't fit the layout
+    let default = MemoryLayout::for_platform(Platform::Chip8);
+    assert!(Disk::from_intel_hex(&disk.to_intel_hex(default), MemoryLayout::ETI_660).is_err());
+
+    // Only the memory after 0x600 holds the program
+    let path = write_rom("eti660-large", &[0x12; 0xA01]);
+    let result = Disk::load_with_layout(path.to_str().unwrap(), MemoryLayout::ETI_660);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(DiskError::TooLarge {
+            size: 0xA01,
+            max: 0xA00
+        })
+    ));
+}
@@ -0,0 +1,93 @@
+use super::{AntiFlicker, FrameBlender};
+use crate::emulation::Framebuffer;
+
+const PALETTE: [u32; 4] = [0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00];
+
+// Red channel of a pixel in the first row
+fn red(blender: &FrameBlender, x: usize) -> u8 {
+    blender.to_rgba(&PALETTE)[x * 4]
+}
+
+#[test]
+fn flicker_from_name() {
+    assert_eq!(AntiFlicker::from_name("OFF"), Some(AntiFlicker::Off));
+    assert_eq!(AntiFlicker::from_name("or"), Some(AntiFlicker::Or));
+    assert_eq!(
+        AntiFlicker::from_name("blend"),
+        Some(AntiFlicker::Blend { frames: 3 })
+    );
+    assert_eq!(AntiFlicker::from_name("smear"), None);
+}
+
+// Without anti-flicker the picture is the framebuffer
+#[test]
+fn flicker_off() {
+    let mut framebuffer = Framebuffer::default();
+    let mut blender = FrameBlender::new(AntiFlicker::Off);
+    framebuffer.set_pixel(1, 0, 1);
+    blender.push(&mut framebuffer);
+    assert_eq!(blender.to_rgba(&PALETTE), framebuffer.to_rgba(&PALETTE));
+    assert_eq!((blender.width(), blender.height()), (64, 32));
+}
+
+// An erased pixel stays visible for one more frame
+#[test]
+fn flicker_or() {
+    let mut framebuffer = Framebuffer::default();
+    let mut blender = FrameBlender::new(AntiFlicker::Or);
+    framebuffer.set_pixel(0, 0, 1);
+    blender.push(&mut framebuffer);
+
+    framebuffer.set_pixel(0, 0, 0);
+    blender.push(&mut framebuffer);
+    assert_eq!(red(&blender, 0), 0xFF);
+
+    blender.push(&mut framebuffer);
+    assert_eq!(red(&blender, 0), 0x00);
+}
+
+// An erased pixel fades out over the blended frames
+#[test]
+fn flicker_blend() {
+    let mut framebuffer = Framebuffer::default();
+    let mut blender = FrameBlender::new(AntiFlicker::Blend { frames: 4 });
+    framebuffer.set_pixel(0, 0, 1);
+    blender.push(&mut framebuffer);
+    assert_eq!(red(&blender, 0), 0xFF);
+
+    framebuffer.set_pixel(0, 0, 0);
+    let mut intensities = Vec::new();
+    for _ in 0..4 {
+        blender.push(&mut framebuffer);
+        intensities.push(red(&blender, 0));
+    }
+    assert_eq!(intensities, [191, 128, 64, 0]);
+}
+
+// The picture changes while pixels fade, even with a clean framebuffer
+#[test]
+fn flicker_changed() {
+    let mut framebuffer = Framebuffer::default();
+    let mut blender = FrameBlender::new(AntiFlicker::Or);
+    blender.push(&mut framebuffer);
+    assert!(blender.take_changed());
+    blender.push(&mut framebuffer);
+    assert!(!blender.take_changed());
+
+    framebuffer.set_pixel(0, 0, 1);
+    framebuffer.set_pixel(0, 0, 0);
+    blender.push(&mut framebuffer);
+    assert!(blender.take_changed());
+
+    framebuffer.set_pixel(0, 0, 1);
+    blender.push(&mut framebuffer);
+    assert!(blender.take_changed());
+    framebuffer.set_pixel(0, 0, 0);
+    framebuffer.take_dirty();
+    blender.push(&mut framebuffer);
+    assert!(blender.take_changed());
+    blender.push(&mut framebuffer);
+    assert!(blender.take_changed());
+    blender.push(&mut framebuffer);
+    assert!(!blender.take_changed());
+}
@@ -0,0 +1,106 @@
+use super::{Framebuffer, Resolution};
+
+#[test]
+fn framebuffer_resolutions() {
+    let mut framebuffer = Framebuffer::default();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (64, 32));
+    assert_eq!(framebuffer.pixels().len(), 64 * 32);
+
+    framebuffer.set_pixel(1, 1, 1);
+    framebuffer.set_resolution(Resolution::Tall);
+    assert_eq!((framebuffer.width(), framebuffer.height()), (64, 64));
+    assert_eq!(framebuffer.pixel(1, 1), 0);
+
+    framebuffer.set_resolution(Resolution::Hires);
+    assert_eq!(framebuffer.rows().count(), 64);
+    assert!(framebuffer.rows().all(|row| row.len() == 128));
+}
+
+// Drawing a sprite twice erases it and reports the collision
+#[test]
+fn framebuffer_draw_sprite() {
+    let mut framebuffer = Framebuffer::default();
+    assert!(!framebuffer.draw_sprite(2, 3, 8, &[0b1000_0001], 1, false));
+    assert_eq!(framebuffer.pixel(2, 3), 1);
+    assert_eq!(framebuffer.pixel(9, 3), 1);
+    assert_eq!(framebuffer.pixel(3, 3), 0);
+
+    assert!(framebuffer.draw_sprite(2, 3, 8, &[0b1000_0000], 1, false));
+    assert_eq!(framebuffer.pixel(2, 3), 0);
+}
+
+// Planes are drawn independently, a set pixel of another plane is no collision
+#[test]
+fn framebuffer_draw_sprite_planes() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.draw_sprite(0, 0, 8, &[0x80], 1, false);
+    assert!(!framebuffer.draw_sprite(0, 0, 8, &[0x80], 2, false));
+    assert_eq!(framebuffer.pixel(0, 0), 3);
+}
+
+// Sprites wrap around the edges unless they are clipped, the start position always wraps
+#[test]
+fn framebuffer_draw_sprite_edges() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.draw_sprite(63, 31, 16, &[0xC000, 0xC000], 1, false);
+    assert_eq!(framebuffer.pixel(63, 31), 1);
+    assert_eq!(framebuffer.pixel(0, 31), 1);
+    assert_eq!(framebuffer.pixel(63, 0), 1);
+    assert_eq!(framebuffer.pixel(0, 0), 1);
+
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.draw_sprite(64 + 63, 31, 16, &[0xC000, 0xC000], 1, true);
+    assert_eq!(framebuffer.pixel(63, 31), 1);
+    assert_eq!(framebuffer.pixel(0, 31), 0);
+    assert_eq!(framebuffer.pixel(63, 0), 0);
+}
+
+#[test]
+fn framebuffer_clear() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.set_pixel(0, 0, 3);
+    framebuffer.clear(1);
+    assert_eq!(framebuffer.pixel(0, 0), 2);
+    framebuffer.clear(3);
+    assert_eq!(framebuffer.pixel(0, 0), 0);
+}
+
+#[test]
+fn framebuffer_scroll() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.set_pixel(5, 0, 3);
+    framebuffer.scroll(0, 2, 1);
+    assert_eq!(framebuffer.pixel(5, 0), 2);
+    assert_eq!(framebuffer.pixel(5, 2), 1);
+
+    framebuffer.scroll(-6, 0, 3);
+    assert_eq!(framebuffer.pixel(5, 0), 0);
+    assert_eq!(framebuffer.pixel(5, 2), 0);
+}
+
+// Changes mark the framebuffer dirty until the renderer takes it
+#[test]
+fn framebuffer_dirty() {
+    let mut framebuffer = Framebuffer::default();
+    assert!(framebuffer.take_dirty());
+    assert!(!framebuffer.is_dirty());
+    assert!(!framebuffer.take_dirty());
+
+    framebuffer.draw_sprite(0, 0, 8, &[0x80], 1, false);
+    assert!(framebuffer.take_dirty());
+    framebuffer.mark_dirty();
+    assert!(framebuffer.is_dirty());
+}
+
+// Every pixel becomes the palette colour of its planes
+#[test]
+fn framebuffer_to_rgba() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.set_pixel(1, 0, 1);
+    framebuffer.set_pixel(2, 0, 3);
+    let rgba = framebuffer.to_rgba(&[0x000000, 0x112233, 0x445566, 0x778899]);
+    assert_eq!(rgba.len(), 64 * 32 * 4);
+    assert_eq!(rgba[0..4], [0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(rgba[4..8], [0x11, 0x22, 0x33, 0xFF]);
+    assert_eq!(rgba[8..12], [0x77, 0x88, 0x99, 0xFF]);
+}
@@ -0,0 +1,101 @@
+use super::{from_intel, from_plain, is_intel, to_intel, to_plain};
+use crate::emulation::DiskError;
+
+// Line of a parse error, if the result is one
+fn error_line(result: Result<Vec<u8>, DiskError>) -> Option<usize> {
+    match result {
+        Err(DiskError::Parse { line, .. }) => Some(line),
+        _ => None,
+    }
+}
+
+#[test]
+fn hex_plain_round_trip() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    let text = to_plain(&bytes);
+    assert!(text.starts_with("00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n10 11"));
+    assert_eq!(from_plain(&text).unwrap(), bytes);
+}
+
+// Pasted dumps may be formatted in many ways
+#[test]
+fn hex_plain_tolerant() {
+    let text = "
+        ; clear the screen
+        00 e0   # CLS
+        0x12, 0x00 // JP 0x200
+        A2FF
+    ";
+    assert_eq!(
+        from_plain(text).unwrap(),
+        [0x00, 0xE0, 0x12, 0x00, 0xA2, 0xFF]
+    );
+}
+
+#[test]
+fn hex_plain_errors() {
+    assert_eq!(error_line(from_plain("00 e0\n0g")), Some(2));
+    assert_eq!(error_line(from_plain("00 e")), Some(1));
+}
+
+// Records use the memory address, above 64 KiB with an extended address
+#[test]
+fn hex_intel_round_trip() {
+    let bytes: Vec<u8> = (0..0x30).collect();
+    let text = to_intel(&bytes, 0x200);
+    assert!(text.starts_with(":10020000000102030405060708090A0B0C0D0E0F76\n"));
+    assert!(text.ends_with(":00000001FF\n"));
+    assert!(is_intel(&text));
+    assert_eq!(from_intel(&text, 0x200, 0x10000).unwrap(), bytes);
+
+    let bytes = vec![0x42; 0x10000];
+    let text = to_intel(&bytes, 0x200);
+    assert!(text.contains(":020000040001F9\n"));
+    assert_eq!(from_intel(&text, 0x200, 0x10000).unwrap(), bytes);
+}
+
+#[test]
+fn hex_intel_errors() {
+    // Checksum
+    assert_eq!(
+        error_line(from_intel(":0102000000FC\n:00000001FF", 0x200, 0x10000)),
+        Some(1)
+    );
+    // Below the load address
+    assert_eq!(
+        error_line(from_intel(":0101FF0000FF\n:00000001FF", 0x200, 0x10000)),
+        Some(1)
+    );
+    // No end of file
+    assert_eq!(
+        error_line(from_intel(":0102000000FD", 0x200, 0x10000)),
+        Some(1)
+    );
+    assert!(!is_intel("00 e0"));
+}
+
+// Data past the end of memory is refused before anything is allocated
+#[test]
+fn hex_intel_too_large() {
+    let text = ":02000004FFFFFC\n:0100000042BD\n:00000001FF";
+    assert!(matches!(
+        from_intel(text, 0x200, 0xE00),
+        Err(DiskError::TooLarge {
+            size: 0xFFFE_FE01,
+            max: 0xE00
+        })
+    ));
+
+    let text = ":020E00000102ED\n:00000001FF";
+    assert!(matches!(
+        from_intel(text, 0x200, 0xC00),
+        Err(DiskError::TooLarge {
+            size: 0xC02,
+            max: 0xC00
+        })
+    ));
+    assert_eq!(
+        from_intel(text, 0x200, 0xC02).unwrap()[0xC00..],
+        [0x01, 0x02]
+    );
+}
@@ -0,0 +1,22 @@
+use super::{keyboard_char, keypad_key};
+
+// The QWERTY layout maps onto the hex keypad, X is key 0
+#[test]
+fn input_keypad_key() {
+    assert_eq!(keypad_key('1'), Some(0x1));
+    assert_eq!(keypad_key('4'), Some(0xc));
+    assert_eq!(keypad_key('X'), Some(0x0));
+    assert_eq!(keypad_key('z'), Some(0xa));
+    assert_eq!(keypad_key('y'), Some(0xa));
+    assert_eq!(keypad_key('v'), Some(0xf));
+    assert_eq!(keypad_key('p'), None);
+}
+
+// Every keypad key maps back to the character it comes from
+#[test]
+fn input_keyboard_char() {
+    for key in 0..16 {
+        assert_eq!(keypad_key(keyboard_char(key)), Some(key));
+    }
+    assert_eq!(keyboard_char(0xc), '4');
+}
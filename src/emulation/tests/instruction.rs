@@ -0,0 +1,90 @@
+use super::{DecodeError, Instruction, Platform};
+
+// Decode a few opcodes of each group
+#[test]
+fn instruction_decode() {
+    assert_eq!(Instruction::decode(0x00E0), Ok(Instruction::Cls));
+    assert_eq!(
+        Instruction::decode(0x7A12),
+        Ok(Instruction::AddImm { x: 0xA, kk: 0x12 })
+    );
+    assert_eq!(
+        Instruction::decode(0x812E),
+        Ok(Instruction::ShiftLeft { x: 1, y: 2 })
+    );
+    assert_eq!(
+        Instruction::decode(0xD125),
+        Ok(Instruction::Draw { x: 1, y: 2, n: 5 })
+    );
+    assert_eq!(
+        Instruction::decode(0x0123),
+        Ok(Instruction::Sys { nnn: 0x123 })
+    );
+    assert_eq!(Instruction::decode(0xF000), Ok(Instruction::LoadILong));
+}
+
+// Opcodes without an instruction
+#[test]
+fn instruction_decode_unknown() {
+    for opcode in [0x5121, 0x8128, 0x9121, 0xE1FF, 0xF1FF, 0xF100] {
+        assert_eq!(Instruction::decode(opcode), Err(DecodeError { opcode }));
+    }
+}
+
+// Every decodable opcode encodes back to itself
+#[test]
+fn instruction_encode_round_trip() {
+    for opcode in 0..=0xFFFF {
+        if let Ok(instruction) = Instruction::decode(opcode) {
+            assert_eq!(instruction.encode(), opcode, "{}", instruction);
+        }
+    }
+}
+
+// Canonical mnemonics
+#[test]
+fn instruction_display() {
+    let cases = [
+        (0x00EE, "RET"),
+        (0x1234, "JP 0x234"),
+        (0x3A0F, "SE VA, 0x0F"),
+        (0x8125, "SUB V1, V2"),
+        (0xB300, "JP V0, 0x300"),
+        (0xD12F, "DRW V1, V2, 15"),
+        (0xF10A, "LD V1, K"),
+        (0xF255, "LD [I], V2"),
+        (0x00C4, "SCD 4"),
+        (0xF330, "LD HF, V3"),
+        (0x5132, "SAVE V1, V3"),
+        (0xF201, "PLANE 2"),
+    ];
+    for (opcode, mnemonic) in cases {
+        assert_eq!(Instruction::decode(opcode).unwrap().to_string(), mnemonic);
+    }
+}
+
+// Extensions report the platform that introduced them
+#[test]
+fn instruction_platform() {
+    assert_eq!(Instruction::Cls.platform(), Platform::Chip8);
+    assert_eq!(Instruction::HighRes.platform(), Platform::SuperChip);
+    assert_eq!(Instruction::Plane { n: 1 }.platform(), Platform::XoChip);
+    assert_eq!(Instruction::LoadILong.size(), 4);
+}
+
+// Older platforms only decode their own instructions, 00xx words become machine code calls
+#[test]
+fn instruction_decode_for() {
+    assert_eq!(
+        Instruction::decode_for(0x00FF, Platform::SuperChip),
+        Ok(Instruction::HighRes)
+    );
+    assert_eq!(
+        Instruction::decode_for(0x00FF, Platform::Chip8),
+        Ok(Instruction::Sys { nnn: 0x0FF })
+    );
+    assert_eq!(
+        Instruction::decode_for(0xF130, Platform::Chip8),
+        Err(DecodeError { opcode: 0xF130 })
+    );
+}
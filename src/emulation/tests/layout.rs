@@ -0,0 +1,97 @@
+use super::{LayoutError, MemoryLayout};
+use crate::emulation::Platform;
+
+#[test]
+fn layout_defaults() {
+    let layout = MemoryLayout::for_platform(Platform::XoChip);
+    assert_eq!(layout.ram_size, 0x10000);
+    assert_eq!(layout.program_start, 0x200);
+    assert_eq!(layout.max_program_size(), 0x10000 - 0x200);
+    assert_eq!(layout.validate(), Ok(()));
+    assert_eq!(MemoryLayout::ETI_660.validate(), Ok(()));
+    assert_eq!(
+        MemoryLayout::from_name("ETI660", Platform::Chip8),
+        Some(MemoryLayout::ETI_660)
+    );
+}
+
+// Named layouts move the program start within the memory of the platform
+#[test]
+fn layout_from_name_platform() {
+    let layout = MemoryLayout::from_name("eti660", Platform::XoChip).unwrap();
+    assert_eq!(layout.program_start, 0x600);
+    assert_eq!(layout.ram_size, 0x10000);
+    assert_eq!(layout.max_program_size(), 0x10000 - 0x600);
+    assert_eq!(layout.validate(), Ok(()));
+    assert_eq!(MemoryLayout::from_name("amiga", Platform::Chip8), None);
+}
+
+#[test]
+fn layout_validate() {
+    let default = MemoryLayout::for_platform(Platform::Chip8);
+
+    // The font fits at 0x050 once the big font moves out of the way
+    let layout = MemoryLayout {
+        font_address: 0x050,
+        ..default
+    };
+    assert_eq!(layout.validate(), Err(LayoutError::FontsOverlap));
+    let layout = MemoryLayout {
+        font_address: 0x050,
+        big_font_address: 0x0A0,
+        ..default
+    };
+    assert_eq!(layout.validate(), Ok(()));
+
+    let layout = MemoryLayout {
+        big_font_address: 0xFA0,
+        ..default
+    };
+    assert_eq!(
+        layout.validate(),
+        Err(LayoutError::OutOfRange {
+            name: "big font",
+            address: 0xFA0
+        })
+    );
+
+    // Addresses are 16 bits wide
+    let layout = MemoryLayout {
+        ram_size: 0x10001,
+        ..default
+    };
+    assert_eq!(
+        layout.validate(),
+        Err(LayoutError::RamTooLarge { ram_size: 0x10001 })
+    );
+
+    // Fonts can't be overwritten by the program
+    let layout = MemoryLayout {
+        big_font_address: 0x1C0,
+        ..default
+    };
+    assert_eq!(
+        layout.validate(),
+        Err(LayoutError::FontInProgram { name: "big font" })
+    );
+    let layout = MemoryLayout {
+        font_address: 0x800,
+        ..default
+    };
+    assert_eq!(
+        layout.validate(),
+        Err(LayoutError::FontInProgram { name: "font" })
+    );
+
+    let layout = MemoryLayout {
+        reserved: 0x300,
+        ..default
+    };
+    assert_eq!(
+        layout.validate(),
+        Err(LayoutError::ProgramInReservedArea {
+            program_start: 0x200,
+            reserved: 0x300
+        })
+    );
+}
@@ -0,0 +1,279 @@
+use super::compile;
+use crate::emulation::asm::AsmError;
+use crate::emulation::{Cpu, Disk};
+
+// Run a compiled program for a number of steps with registers set up front
+fn run(rom: Vec<u8>, registers: &[(usize, u8)], steps: usize) -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.load_disk_to_ram(&Disk::from_rom(rom)).unwrap();
+    for &(x, value) in registers {
+        cpu.reg_v[x] = value;
+    }
+    for _ in 0..steps {
+        cpu.step().unwrap();
+    }
+    cpu
+}
+
+fn error(line: usize, column: usize, message: &str) -> AsmError {
+    AsmError {
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+// A program starting with main needs no jump to it, labels resolve forwards
+#[test]
+fn octo_main_first() {
+    let source = "
+        : main
+            v0 := 5
+            v1 += v0    # comment
+            i := dot
+            sprite v0 v1 1
+            loop again
+        : dot
+            0xFF
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0x60, 0x05, 0x81, 0x04, 0xA2, 0x0A, 0xD0, 0x11, 0x12, 0x08, 0xFF
+        ])
+    );
+}
+
+// Otherwise 0x200 jumps to main, names on their own are calls
+#[test]
+fn octo_jump_to_main() {
+    let source = "
+        : draw
+            sprite v0 v0 5
+            return
+        : main
+            draw
+            loop again
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0x12, 0x06, 0xD0, 0x05, 0x00, 0xEE, 0x22, 0x02, 0x12, 0x08
+        ])
+    );
+}
+
+#[test]
+fn octo_statements() {
+    let source = "
+        : main
+            clear  bcd v1  save v2  load v3  save v1 - v4  load v5 - v6
+            saveflags v7  loadflags v8  delay := v9  buzzer := va  pitch := vb
+            v0 := key  v1 := delay  v2 := random 0x0F  v3 := v4  v5 -= 1  v6 -= v7
+            v8 |= v9  va &= vb  vc ^= vd  ve =- v0  v1 >>= v2  v3 <<= v3
+            i := hex v4  i := bighex v5  i += v6  jump0 0x300  native 0x123  :call 0x456
+            plane 3  audio  scroll-down 4  scroll-up 2  scroll-right  scroll-left
+            lores  hires  exit  ;
+    ";
+    let expected: Vec<u16> = vec![
+        0x00E0, 0xF133, 0xF255, 0xF365, 0x5142, 0x5563, 0xF775, 0xF885, 0xF915, 0xFA18, 0xFB3A,
+        0xF00A, 0xF107, 0xC20F, 0x8340, 0x75FF, 0x8675, 0x8891, 0x8AB2, 0x8CD3, 0x8E07, 0x8126,
+        0x833E, 0xF429, 0xF530, 0xF61E, 0xB300, 0x0123, 0x2456, 0xF301, 0xF002, 0x00C4, 0x00D2,
+        0x00FB, 0x00FC, 0x00FE, 0x00FF, 0x00FD, 0x00EE,
+    ];
+    let rom: Vec<u8> = expected.iter().flat_map(|op| op.to_be_bytes()).collect();
+    assert_eq!(compile(source), Ok(rom));
+}
+
+// then skips the next statement unless the condition holds
+#[test]
+fn octo_if_then() {
+    let source = "
+        : main
+            if v0 == 5 then v1 := 1
+            if v2 != v3 then clear
+            if v0 key then v0 := 0
+            if v0 -key then v0 := 1
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0x40, 0x05, 0x61, 0x01, 0x52, 0x30, 0x00, 0xE0, 0xE0, 0xA1, 0x60, 0x00, 0xE0, 0x9E,
+            0x60, 0x01
+        ])
+    );
+}
+
+// begin and else jump over the part that doesn't run
+#[test]
+fn octo_if_begin_else() {
+    let source = "
+        : main
+            if v0 == 1 begin
+                v1 := 1
+            else
+                v1 := 2
+            end
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0x30, 0x01, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0A, 0x61, 0x02
+        ])
+    );
+
+    for (v0, v1) in [(1, 1), (7, 2)] {
+        let cpu = run(compile(source).unwrap(), &[(0, v0)], 3);
+        assert_eq!(cpu.reg_v[1], v1);
+    }
+}
+
+// <, >, <= and >= subtract into vF and test the borrow
+#[test]
+fn octo_compare() {
+    for (compare, results) in [
+        ("<", [true, false, false]),
+        (">", [false, false, true]),
+        ("<=", [true, true, false]),
+        (">=", [false, true, true]),
+    ] {
+        for (value, expected) in [4, 5, 6].into_iter().zip(results) {
+            let source = format!(": main v2 := 0 if v1 {} 5 then v2 := 1 loop again", compare);
+            let cpu = run(compile(&source).unwrap(), &[(1, value)], 6);
+            assert_eq!(cpu.reg_v[2] == 1, expected, "{} {} 5", value, compare);
+
+            // The same against a register
+            let source = format!(
+                ": main v2 := 0 v3 := 5 if v1 {} v3 then v2 := 1 loop again",
+                compare
+            );
+            let cpu = run(compile(&source).unwrap(), &[(1, value)], 7);
+            assert_eq!(cpu.reg_v[2] == 1, expected, "{} {} v3", value, compare);
+        }
+    }
+}
+
+// while leaves the loop once its condition fails
+#[test]
+fn octo_loop_while() {
+    let source = "
+        : main
+            loop
+                while v0 != 10
+                v0 += 1
+            again
+            v1 := 1
+    ";
+    let rom = compile(source).unwrap();
+    assert_eq!(
+        rom,
+        vec![0x40, 0x0A, 0x12, 0x08, 0x70, 0x01, 0x12, 0x00, 0x61, 0x01]
+    );
+
+    let cpu = run(rom, &[], 10 * 3 + 3);
+    assert_eq!(cpu.reg_v[0], 10);
+    assert_eq!(cpu.reg_v[1], 1);
+}
+
+// :const, :alias and :calc, with expressions evaluated right to left
+#[test]
+fn octo_constants() {
+    let source = "
+        :const speed 3
+        :alias counter v4
+        :calc twice { speed * 2 }
+        :calc right-to-left { 10 - 4 - 1 }
+        :calc grouped { ( 10 - 4 ) - 1 }
+        : main
+            counter := twice
+            counter -= speed
+            v0 := right-to-left
+            v1 := grouped
+            :byte { twice + 1 }
+            speed
+            -1
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0x64, 0x06, 0x74, 0xFD, 0x60, 0x07, 0x61, 0x05, 0x07, 0x03, 0xFF
+        ])
+    );
+}
+
+// Macros substitute their arguments, string modes expand once per character
+#[test]
+fn octo_macros() {
+    let source = "
+        :macro add-twice register amount {
+            register += amount
+            register += amount
+        }
+        :stringmode digits \"0123456789\" { :byte { VALUE + INDEX * 16 } }
+        : main
+            add-twice v3 2
+            digits \"907\"
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![0x73, 0x02, 0x73, 0x02, 0x09, 0x10, 0x27])
+    );
+
+    let source = ":macro forever { forever } : main forever";
+    assert!(compile(source).is_err());
+}
+
+// Addresses of labels further down are patched into long loads, pointers and unpacks
+#[test]
+fn octo_addresses() {
+    let source = "
+        : main
+            i := long data
+            :unpack 0xA data
+            :unpack long data
+            :pointer data
+            jump target
+        : target
+        :next data
+            v0 := 0x42
+    ";
+    assert_eq!(
+        compile(source),
+        Ok(vec![
+            0xF0, 0x00, 0x02, 0x11, 0x60, 0xA2, 0x61, 0x11, 0x60, 0x02, 0x61, 0x11, 0x02, 0x11,
+            0x12, 0x10, 0x60, 0x42
+        ])
+    );
+}
+
+#[test]
+fn octo_errors() {
+    assert_eq!(
+        compile(": main\n  jump nowhere"),
+        Err(error(2, 8, "'nowhere' is not defined"))
+    );
+    assert_eq!(
+        compile(": start\n  loop again"),
+        Err(error(2, 1, "program has no main label"))
+    );
+    assert_eq!(
+        compile(": main\n  if v0 == 1 begin\n  clear"),
+        Err(error(2, 3, "if ... begin without end"))
+    );
+    assert_eq!(
+        compile(": main\n  sprite v0 vg 1"),
+        Err(error(2, 13, "expected a register, found 'vg'"))
+    );
+    assert_eq!(
+        compile(": main\n  v0 := 256"),
+        Err(error(2, 9, "byte 256 out of range"))
+    );
+    assert_eq!(
+        compile(": main\n  : main"),
+        Err(error(2, 5, "label 'main' is already defined"))
+    );
+    assert_eq!(
+        compile(": main\n  v0 := \"text"),
+        Err(error(2, 9, "unterminated string"))
+    );
+}
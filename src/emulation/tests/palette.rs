@@ -0,0 +1,36 @@
+use super::{parse_colour, Palette};
+
+#[test]
+fn palette_themes() {
+    let names: Vec<String> = Palette::themes().into_iter().map(|t| t.name).collect();
+    assert_eq!(names, ["default", "green", "amber", "lcd", "high-contrast"]);
+    assert_eq!(Palette::default().name, "default");
+    assert_eq!(
+        Palette::from_name("High-Contrast").unwrap().colours[1],
+        0xFFFFFF
+    );
+    assert_eq!(Palette::from_name("sepia"), None);
+}
+
+#[test]
+fn palette_parse_colour() {
+    assert_eq!(parse_colour("#FFCC00"), Some(0xFFCC00));
+    assert_eq!(parse_colour("0x00ff7f"), Some(0x00FF7F));
+    assert_eq!(parse_colour("123456"), Some(0x123456));
+    assert_eq!(parse_colour("#FFF"), None);
+    assert_eq!(parse_colour("#+FFFFF"), None);
+}
+
+#[test]
+fn palette_parse() {
+    let palette = Palette::parse("mine", "#000000, #111111 #222222 #333333").unwrap();
+    assert_eq!(palette.name, "mine");
+    assert_eq!(palette.colours, [0x000000, 0x111111, 0x222222, 0x333333]);
+
+    // Both planes share the foreground of a two colour palette
+    let palette = Palette::parse("mono", "#000000 #FFFFFF").unwrap();
+    assert_eq!(palette.colours, [0x000000, 0xFFFFFF, 0xFFFFFF, 0xFFFFFF]);
+
+    assert!(Palette::parse("short", "#000000").is_err());
+    assert!(Palette::parse("typo", "#000000 #GGGGGG").is_err());
+}
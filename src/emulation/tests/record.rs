@@ -0,0 +1,78 @@
+use super::{record_frames, GifRecorder};
+use crate::emulation::{Cpu, Disk, Framebuffer, Resolution, Scheduler};
+
+const PALETTE: [u32; 4] = [0x000000, 0x112233, 0x445566, 0x778899];
+
+// Delay and the palette index of the top left pixel of every frame
+fn decode(gif: &[u8]) -> Vec<(u16, u8)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif).unwrap();
+    assert_eq!(decoder.global_palette().unwrap()[3..6], [0x11, 0x22, 0x33]);
+
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((frame.delay, frame.buffer[0]));
+    }
+    frames
+}
+
+// Unchanged frames only make the shown frame last longer
+#[test]
+fn record_duplicate_frames() {
+    let mut framebuffer = Framebuffer::default();
+    let mut recorder = GifRecorder::for_framebuffer(Vec::new(), &framebuffer, 2, &PALETTE).unwrap();
+    for _ in 0..6 {
+        recorder.push(&framebuffer).unwrap();
+    }
+    framebuffer.set_pixel(0, 0, 1);
+    for _ in 0..3 {
+        recorder.push(&framebuffer).unwrap();
+    }
+    let gif = recorder.finish().unwrap();
+    assert_eq!(decode(&gif), [(10, 0), (5, 1)]);
+}
+
+// Frames changing at 60 Hz are too short for a GIF, every other one is left out
+#[test]
+fn record_short_frames() {
+    let mut framebuffer = Framebuffer::default();
+    let mut recorder = GifRecorder::for_framebuffer(Vec::new(), &framebuffer, 1, &PALETTE).unwrap();
+    for index in 0..6 {
+        framebuffer.set_pixel(0, 0, index % 2);
+        recorder.push(&framebuffer).unwrap();
+    }
+    let frames = decode(&recorder.finish().unwrap());
+    assert!(frames.iter().all(|(delay, _)| *delay >= 2));
+    assert_eq!(frames.iter().map(|(delay, _)| delay).sum::<u16>(), 10);
+}
+
+// A resolution switch is scaled to the size of the recording
+#[test]
+fn record_resolution_switch() {
+    let mut framebuffer = Framebuffer::default();
+    let mut recorder = GifRecorder::for_framebuffer(Vec::new(), &framebuffer, 2, &PALETTE).unwrap();
+    recorder.push(&framebuffer).unwrap();
+    framebuffer.set_resolution(Resolution::Hires);
+    framebuffer.set_pixel(0, 0, 2);
+    recorder.push(&framebuffer).unwrap();
+    let frames = decode(&recorder.finish().unwrap());
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].1, 2);
+}
+
+// Headless recording of a program drawing a digit
+#[test]
+fn record_headless() {
+    // LD V0, 0; LD F, V0; DRW V0, V0, 5; JP 0x206
+    let disk = Disk::from_rom(vec![0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06]);
+    let mut cpu = Cpu::new();
+    cpu.load_disk_to_ram(&disk).unwrap();
+    let mut scheduler = Scheduler::new(10);
+    let mut recorder =
+        GifRecorder::for_framebuffer(Vec::new(), &cpu.framebuffer, 1, &PALETTE).unwrap();
+
+    record_frames(&mut cpu, &mut scheduler, &mut recorder, 30).unwrap();
+    let frames = decode(&recorder.finish().unwrap());
+    assert_eq!(frames, [(50, 1)]);
+}
@@ -0,0 +1,21 @@
+use super::{sha1_hex, RomInfo};
+use crate::emulation::{Platform, Quirks};
+
+#[test]
+fn romdb_sha1_hex() {
+    assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+}
+
+// Known ROMs are found by their content, not their file name
+#[test]
+fn romdb_lookup() {
+    let tetris = include_bytes!("../../../roms/Tetris_[Fran_Dachille,1991].ch8");
+    let info = RomInfo::lookup(tetris).unwrap();
+    assert_eq!(info.title, "Tetris");
+    assert_eq!(info.platform, Platform::Chip8);
+    assert_eq!(info.quirks, Quirks::CHIP48);
+    assert_eq!(info.keys[0], (0x4, "rotate"));
+
+    assert_eq!(RomInfo::lookup(&[0x12, 0x00]), None);
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use super::Scheduler;
+use crate::emulation::{Cpu, Disk};
+
+// Cpu running an endless loop of ADD V0, 1
+fn get_counting_cpu() -> Cpu {
+    let disk = Disk::from_rom(vec![0x70, 0x01, 0x12, 0x00]);
+    let mut cpu = Cpu::new();
+    cpu.load_disk_to_ram(&disk).unwrap();
+    cpu
+}
+
+// A frame runs the instruction budget and ticks the timers once
+#[test]
+fn scheduler_run_frame() {
+    let mut cpu = get_counting_cpu();
+    cpu.reg_delay_timer = 5;
+    let mut scheduler = Scheduler::new(8);
+
+    scheduler.run_frame(&mut cpu).unwrap();
+    assert_eq!(cpu.reg_v[0], 4);
+    assert_eq!(cpu.reg_delay_timer, 4);
+}
+
+// One second of wall clock time ticks the timers exactly 60 times
+#[test]
+fn scheduler_advance_one_second() {
+    let mut cpu = get_counting_cpu();
+    cpu.reg_delay_timer = 0xFF;
+    let mut scheduler = Scheduler::new(2);
+
+    let mut frames = 0;
+    for _ in 0..10 {
+        frames += scheduler
+            .advance(&mut cpu, Duration::from_millis(100))
+            .unwrap();
+    }
+    assert_eq!(frames, 60);
+    assert_eq!(cpu.reg_delay_timer, 0xFF - 60);
+    assert_eq!(cpu.reg_v[0], 60);
+}
+
+// Partial frames are carried over to the next call
+#[test]
+fn scheduler_advance_accumulates() {
+    let mut cpu = get_counting_cpu();
+    let mut scheduler = Scheduler::new(2);
+
+    assert_eq!(
+        scheduler
+            .advance(&mut cpu, Duration::from_millis(10))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        scheduler
+            .advance(&mut cpu, Duration::from_millis(10))
+            .unwrap(),
+        1
+    );
+}
+
+// Long stalls don't make the cpu run ahead
+#[test]
+fn scheduler_advance_caps_catch_up() {
+    let mut cpu = get_counting_cpu();
+    let mut scheduler = Scheduler::new(2);
+
+    let frames = scheduler.advance(&mut cpu, Duration::from_secs(5)).unwrap();
+    assert_eq!(frames, 10);
+    assert_eq!(
+        scheduler
+            .advance(&mut cpu, Duration::from_millis(1))
+            .unwrap(),
+        0
+    );
+}
@@ -0,0 +1,56 @@
+use std::fs;
+
+use super::{next_free_path, to_pbm, to_png};
+use crate::emulation::{Framebuffer, Resolution};
+
+const PALETTE: [u32; 4] = [0x000000, 0x112233, 0x445566, 0x778899];
+
+// The PNG decodes to the scaled framebuffer in palette colours
+#[test]
+fn screenshot_png() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.set_pixel(1, 0, 1);
+    framebuffer.set_pixel(63, 31, 2);
+    let bytes = to_png(&framebuffer, &PALETTE, 3).unwrap();
+
+    let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgba).unwrap();
+    assert_eq!((info.width, info.height), (192, 96));
+
+    let pixel = |x: usize, y: usize| &rgba[(y * 192 + x) * 4..(y * 192 + x) * 4 + 4];
+    assert_eq!(pixel(2, 0), [0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(pixel(3, 0), [0x11, 0x22, 0x33, 0xFF]);
+    assert_eq!(pixel(5, 2), [0x11, 0x22, 0x33, 0xFF]);
+    assert_eq!(pixel(6, 0), [0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(pixel(191, 95), [0x44, 0x55, 0x66, 0xFF]);
+}
+
+// One bit per pixel at native resolution, any plane counts
+#[test]
+fn screenshot_pbm() {
+    let mut framebuffer = Framebuffer::new(Resolution::Hires);
+    framebuffer.set_pixel(0, 0, 1);
+    framebuffer.set_pixel(9, 0, 2);
+    framebuffer.set_pixel(127, 63, 3);
+    let bytes = to_pbm(&framebuffer);
+
+    let header = b"P4\n128 64\n";
+    assert_eq!(&bytes[..header.len()], header);
+    let data = &bytes[header.len()..];
+    assert_eq!(data.len(), 16 * 64);
+    assert_eq!(data[0..2], [0x80, 0x40]);
+    assert_eq!(data[data.len() - 1], 0x01);
+}
+
+#[test]
+fn screenshot_next_free_path() {
+    let dir = std::env::temp_dir().join(format!("chip8-screenshot-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let first = next_free_path(&dir, "Maze", "png");
+    assert_eq!(first, dir.join("Maze-1.png"));
+    fs::write(&first, []).unwrap();
+    assert_eq!(next_free_path(&dir, "Maze", "png"), dir.join("Maze-2.png"));
+    assert_eq!(next_free_path(&dir, "Maze", "pbm"), dir.join("Maze-1.pbm"));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,84 @@
+use super::{Settings, SettingsError};
+use crate::emulation::{AntiFlicker, Palette, Scaling};
+
+#[test]
+fn settings_default() {
+    let settings = Settings::parse("# nothing set\n\n").unwrap();
+    assert_eq!(settings, Settings::default());
+    assert_eq!(settings.anti_flicker, AntiFlicker::Off);
+    assert_eq!(settings.screenshot_scale, 8);
+}
+
+#[test]
+fn settings_scales() {
+    let settings = Settings::parse("screenshot_scale = 3\nrecording_scale = 2").unwrap();
+    assert_eq!(settings.screenshot_scale, 3);
+    assert_eq!(settings.recording_scale, 2);
+}
+
+#[test]
+fn settings_anti_flicker() {
+    let settings = Settings::parse("anti_flicker = or").unwrap();
+    assert_eq!(settings.anti_flicker, AntiFlicker::Or);
+
+    let settings = Settings::parse("blend_frames = 5\nanti_flicker=blend # phosphor").unwrap();
+    assert_eq!(settings.anti_flicker, AntiFlicker::Blend { frames: 5 });
+}
+
+// Errors name the line
+#[test]
+fn settings_errors() {
+    for (text, expected_line) in [
+        ("anti_flicker = smear", 1),
+        ("\nblend_frames = 0", 2),
+        ("anti_flicker", 1),
+        ("\n\nvolume = 11", 3),
+        ("palette.dull = #000000", 1),
+        ("screenshot_scale = 0", 1),
+        ("recording_scale = big", 1),
+        ("scaling = stretch", 1),
+        ("fullscreen = yes", 1),
+        ("theme = sepia\npalette.paper = #FFFFFF #000000", 1),
+    ] {
+        match Settings::parse(text) {
+            Err(SettingsError::Parse { line, .. }) => assert_eq!(line, expected_line),
+            other => panic!("expected a parse error for {:?}, got {:?}", text, other),
+        }
+    }
+}
+
+#[test]
+fn settings_theme() {
+    let settings = Settings::parse("theme = Amber").unwrap();
+    assert_eq!(settings.theme.as_deref(), Some("amber"));
+    assert_eq!(settings.all_palettes(), Palette::themes());
+}
+
+// Palettes from the settings come after the built-in themes and can replace them
+#[test]
+fn settings_palettes() {
+    let text = "theme = paper\npalette.paper = #FFFFFF #000000\npalette.lcd = 0 1 2 3";
+    assert!(Settings::parse(text).is_err());
+
+    let text =
+        "theme = paper\npalette.paper = #FFFFFF #000000\npalette.lcd = 000000 111111 222222 333333";
+    let settings = Settings::parse(text).unwrap();
+    let names: Vec<String> = settings
+        .all_palettes()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(
+        names,
+        ["default", "green", "amber", "high-contrast", "paper", "lcd"]
+    );
+    assert_eq!(settings.palettes[0].colours, [0xFFFFFF, 0, 0, 0]);
+}
+
+#[test]
+fn settings_window() {
+    let settings = Settings::parse("scaling = Fit\nfullscreen = true").unwrap();
+    assert_eq!(settings.scaling, Scaling::Fit);
+    assert!(settings.fullscreen);
+    assert_eq!(Settings::default().scaling, Scaling::Integer);
+}
@@ -0,0 +1,68 @@
+use super::{KeyTimeouts, TextRendering};
+use crate::emulation::{Cpu, Framebuffer, Resolution};
+
+#[test]
+fn terminal_from_name() {
+    assert_eq!(
+        TextRendering::from_name("half-block"),
+        Some(TextRendering::HalfBlock)
+    );
+    assert_eq!(
+        TextRendering::from_name("Braille"),
+        Some(TextRendering::Braille)
+    );
+    assert_eq!(TextRendering::from_name("ascii"), None);
+}
+
+// Two rows of pixels per line
+#[test]
+fn terminal_half_block() {
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.set_pixel(0, 0, 1);
+    framebuffer.set_pixel(1, 1, 2);
+    framebuffer.set_pixel(2, 0, 1);
+    framebuffer.set_pixel(2, 1, 1);
+    let lines = TextRendering::HalfBlock.render(&framebuffer);
+
+    assert_eq!(lines.len(), 16);
+    assert!(lines.iter().all(|line| line.chars().count() == 64));
+    assert!(lines[0].starts_with("\u{2580}\u{2584}\u{2588} "));
+    assert_eq!(lines[1].trim(), "");
+}
+
+// Eight pixels per character
+#[test]
+fn terminal_braille() {
+    let mut framebuffer = Framebuffer::new(Resolution::Hires);
+    framebuffer.set_pixel(0, 0, 1);
+    framebuffer.set_pixel(1, 3, 1);
+    framebuffer.set_pixel(127, 63, 3);
+    let lines = TextRendering::Braille.render(&framebuffer);
+
+    assert_eq!(lines.len(), 16);
+    assert!(lines.iter().all(|line| line.chars().count() == 64));
+    assert_eq!(lines[0].chars().next(), Some('\u{2881}'));
+    assert_eq!(lines[15].chars().last(), Some('\u{2880}'));
+    assert_eq!(lines[1].chars().next(), Some('\u{2800}'));
+}
+
+// Keys come up again after the hold time or when released
+#[test]
+fn terminal_key_timeouts() {
+    let mut cpu = Cpu::new();
+    let mut keys = KeyTimeouts::new(2);
+    keys.press(&mut cpu, 0x5);
+    keys.tick(&mut cpu);
+    assert!(cpu.keyboard[0x5]);
+    keys.press(&mut cpu, 0x5);
+    keys.tick(&mut cpu);
+    assert!(cpu.keyboard[0x5]);
+    keys.tick(&mut cpu);
+    assert!(!cpu.keyboard[0x5]);
+
+    keys.press(&mut cpu, 0xA);
+    keys.release(&mut cpu, 0xA);
+    assert!(!cpu.keyboard[0xA]);
+    keys.tick(&mut cpu);
+    assert!(!cpu.keyboard[0xA]);
+}
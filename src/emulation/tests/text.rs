@@ -0,0 +1,14 @@
+use super::{glyph, pixels};
+
+#[test]
+fn text_glyph() {
+    assert_eq!(glyph('a'), glyph('A'));
+    assert_eq!(glyph('\u{e9}'), glyph('?'));
+    assert_eq!(glyph(' '), [0; 5]);
+}
+
+// Characters sit next to each other with a blank column in between
+#[test]
+fn text_pixels() {
+    assert_eq!(pixels("-."), vec![(0, 2), (1, 2), (2, 2), (5, 4)]);
+}
@@ -0,0 +1,37 @@
+use super::{viewport, Scaling};
+
+#[test]
+fn viewport_from_name() {
+    assert_eq!(Scaling::from_name("Integer"), Some(Scaling::Integer));
+    assert_eq!(Scaling::from_name("fit"), Some(Scaling::Fit));
+    assert_eq!(Scaling::from_name("stretch"), None);
+}
+
+// The default window is filled exactly in low and high resolution
+#[test]
+fn viewport_exact() {
+    let lores = viewport([1024.0, 512.0], [64, 32], Scaling::Integer);
+    assert_eq!(lores, [0.0, 0.0, 1024.0, 512.0]);
+    let hires = viewport([1024.0, 512.0], [128, 64], Scaling::Integer);
+    assert_eq!(hires, [0.0, 0.0, 1024.0, 512.0]);
+}
+
+// Integer scaling leaves bars on all sides, fit scaling only on two
+#[test]
+fn viewport_letterbox() {
+    let integer = viewport([1000.0, 700.0], [64, 32], Scaling::Integer);
+    assert_eq!(integer, [20.0, 110.0, 960.0, 480.0]);
+    let fit = viewport([1000.0, 700.0], [64, 32], Scaling::Fit);
+    assert_eq!(fit, [0.0, 100.0, 1000.0, 500.0]);
+
+    // Two page CHIP-8 is square
+    let tall = viewport([1024.0, 512.0], [64, 64], Scaling::Integer);
+    assert_eq!(tall, [256.0, 0.0, 512.0, 512.0]);
+}
+
+// Integer scaling never goes below one window pixel per CHIP-8 pixel
+#[test]
+fn viewport_tiny_window() {
+    let tiny = viewport([100.0, 40.0], [128, 64], Scaling::Integer);
+    assert_eq!(tiny[2..], [128.0, 64.0]);
+}
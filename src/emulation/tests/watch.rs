@@ -0,0 +1,48 @@
+use std::{fs, path::PathBuf};
+
+use super::{FileWatcher, POLL_INTERVAL};
+
+fn temp_rom(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("chip8-watch-{}-{}", std::process::id(), name));
+    fs::write(&path, [0x12, 0x00]).unwrap();
+    path
+}
+
+// An untouched file never counts as changed
+#[test]
+fn watch_unchanged() {
+    let path = temp_rom("unchanged.ch8");
+    let mut watcher = FileWatcher::new(&path);
+    assert!(!watcher.poll(POLL_INTERVAL));
+    assert!(!watcher.poll(POLL_INTERVAL));
+    fs::remove_file(path).unwrap();
+}
+
+// A change is reported once after it settled for a poll interval
+#[test]
+fn watch_changed() {
+    let path = temp_rom("changed.ch8");
+    let mut watcher = FileWatcher::new(&path);
+    fs::write(&path, [0x60, 0x01, 0x12, 0x02]).unwrap();
+
+    assert!(!watcher.poll(POLL_INTERVAL / 2));
+    assert!(!watcher.poll(POLL_INTERVAL / 2));
+    assert!(watcher.poll(POLL_INTERVAL));
+    assert!(!watcher.poll(POLL_INTERVAL));
+    fs::remove_file(path).unwrap();
+}
+
+// Deleting the file is no change to reload, writing it again is
+#[test]
+fn watch_removed() {
+    let path = temp_rom("removed.ch8");
+    let mut watcher = FileWatcher::new(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(!watcher.poll(POLL_INTERVAL));
+    assert!(!watcher.poll(POLL_INTERVAL));
+
+    fs::write(&path, [0x60, 0x01, 0x12, 0x02]).unwrap();
+    assert!(!watcher.poll(POLL_INTERVAL));
+    assert!(watcher.poll(POLL_INTERVAL));
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+#[path = "./tests/viewport.rs"]
+mod tests;
+
+// How the picture grows with the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scaling {
+    // Whole multiples of the CHIP-8 pixels only, every pixel the same size
+    #[default]
+    Integer,
+    // As large as the window allows, keeping the aspect ratio
+    Fit,
+}
+
+impl Scaling {
+    pub fn from_name(name: &str) -> Option<Scaling> {
+        match name.to_lowercase().as_str() {
+            "integer" => Some(Scaling::Integer),
+            "fit" => Some(Scaling::Fit),
+            _ => None,
+        }
+    }
+}
+
+// Area [x, y, width, height] of a window of window_size the picture is drawn to,
+// centred with letterbox bars around it
+pub fn viewport(window_size: [f64; 2], picture_size: [usize; 2], scaling: Scaling) -> [f64; 4] {
+    let [window_width, window_height] = window_size;
+    let picture_width = picture_size[0].max(1) as f64;
+    let picture_height = picture_size[1].max(1) as f64;
+
+    let fit = (window_width / picture_width).min(window_height / picture_height);
+    let scale = match scaling {
+        // A window smaller than the picture still shows it, just cut off
+        Scaling::Integer => fit.floor().max(1.0),
+        Scaling::Fit => fit,
+    };
+    let width = picture_width * scale;
+    let height = picture_height * scale;
+    [
+        ((window_width - width) / 2.0).floor(),
+        ((window_height - height) / 2.0).floor(),
+        width,
+        height,
+    ]
+}
@@ -0,0 +1,64 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(test)]
+#[path = "./tests/watch.rs"]
+mod tests;
+
+// How often the file is looked at
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Modification time and size of a file, None while it doesn't exist
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Notices when a file changes on disk by polling its metadata, no OS notifications needed.
+// A change is only reported once the file stayed the same for a whole poll interval,
+// so a ROM still being written by an assembler isn't picked up half done.
+pub struct FileWatcher {
+    pub path: PathBuf,
+    // Stamp of the version that was reported last
+    loaded: Stamp,
+    // Stamp seen at the last poll
+    seen: Stamp,
+    since_poll: Duration,
+}
+
+impl FileWatcher {
+    // Start watching with the current version of the file counting as loaded
+    pub fn new(path: &Path) -> FileWatcher {
+        let current = stamp(path);
+        FileWatcher {
+            path: path.to_path_buf(),
+            loaded: current,
+            seen: current,
+            since_poll: Duration::ZERO,
+        }
+    }
+
+    // Call regularly with the time since the last call,
+    // returns true once per settled change of an existing file
+    pub fn poll(&mut self, elapsed: Duration) -> bool {
+        self.since_poll += elapsed;
+        if self.since_poll < POLL_INTERVAL {
+            return false;
+        }
+        self.since_poll = Duration::ZERO;
+
+        let current = stamp(&self.path);
+        let settled = current == self.seen;
+        self.seen = current;
+        if settled && current.is_some() && current != self.loaded {
+            self.loaded = current;
+            return true;
+        }
+        false
+    }
+}
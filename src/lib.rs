@@ -0,0 +1,7 @@
+// CHIP-8 emulation core without a windowing dependency.
+// The piston frontend is only built with the "piston" feature.
+pub mod emulation;
+
+pub use emulation::{
+    Cpu, CpuError, Disk, DiskError, Framebuffer, Instruction, Platform, Quirks, RomInfo, Scheduler,
+};
@@ -1,51 +1,502 @@
-use emulation::handle_input;
-use piston_window::{types::Color, *};
-
-mod emulation;
+use chip8_rust::emulation::{self, handle_input, screenshot};
+use piston_window::*;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 struct Config {
     pub width: u32,
     pub height: u32,
     pub scale: u32,
-    pub ram_size: usize,
+    pub instructions_per_frame: u32,
 }
 
 const DEFAULT_CONFIG: Config = Config {
     width: 64,
     height: 32,
     scale: 16,
-    ram_size: 4096,
+    instructions_per_frame: emulation::DEFAULT_INSTRUCTIONS_PER_FRAME,
 };
 
-// 26 28 44
-const BACKCOLOR: Color = [0.1, 0.11, 0.17, 1.0];
-// 37 113 121
-const FRONTCOLOR: Color = [0.14, 0.44, 0.47, 1.0];
+// Read when no settings file is given
+const DEFAULT_SETTINGS_PATH: &str = "chip8.cfg";
+
+// Settings from the command line and the settings file, they apply to every ROM that gets loaded
+struct Options {
+    platform: Option<emulation::Platform>,
+    quirks: Option<emulation::Quirks>,
+    instructions_per_frame: Option<u32>,
+    seed: Option<u64>,
+    layout_name: String,
+    ram_size: Option<usize>,
+    watch: bool,
+    settings: emulation::Settings,
+}
+
+// A loaded ROM and the state of its run
+struct Session {
+    path: PathBuf,
+    cpu: emulation::Cpu,
+    scheduler: emulation::Scheduler,
+    // Frames on their way to the display
+    blender: emulation::FrameBlender,
+    halted: Option<emulation::CpuError>,
+    colours: Option<[u32; 4]>,
+    // Reloads the ROM when the file changes in watch mode
+    watcher: Option<emulation::FileWatcher>,
+    // GIF being recorded and where it goes
+    recording: Option<(PathBuf, emulation::GifRecorder<BufWriter<File>>)>,
+}
 
 fn main() {
+    // Optional platform: --platform chip8|schip|xochip
+    // Optional quirks preset: --quirks vip|chip48|schip|modern
+    // Optional speed: --ipf <instructions per frame>
+    // Optional random seed for reproducible runs: --seed <number>
+    // Optional memory layout: --layout default|eti660, --ram-size <bytes>
+    // Optional reload of the ROM whenever its file changes: --watch
+    // Optional settings file: --config <path>, chip8.cfg is read if it exists
+    // Optional ROM path or Octo .gif cartridge, settings of known ROMs come from the ROM database.
+    // A directory, by default roms, opens the ROM browser instead.
+    let mut options = Options {
+        platform: None,
+        quirks: None,
+        instructions_per_frame: None,
+        seed: None,
+        layout_name: String::from("default"),
+        ram_size: None,
+        watch: false,
+        settings: emulation::Settings::default(),
+    };
+    let mut config_path = None;
+    let mut rom_path = String::from("roms");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--platform" {
+            let name = args.next().unwrap_or_default();
+            match emulation::Platform::from_name(&name) {
+                Some(selected) => options.platform = Some(selected),
+                None => println!("Unknown platform: {}", name),
+            }
+        } else if arg == "--quirks" {
+            let name = args.next().unwrap_or_default();
+            match emulation::Quirks::from_name(&name) {
+                Some(preset) => options.quirks = Some(preset),
+                None => println!("Unknown quirks preset: {}", name),
+            }
+        } else if arg == "--ipf" {
+            let value = args.next().unwrap_or_default();
+            match value.parse() {
+                Ok(ipf) => options.instructions_per_frame = Some(ipf),
+                Err(_) => println!("Invalid instructions per frame: {}", value),
+            }
+        } else if arg == "--seed" {
+            let value = args.next().unwrap_or_default();
+            match value.parse() {
+                Ok(number) => options.seed = Some(number),
+                Err(_) => println!("Invalid seed: {}", value),
+            }
+        } else if arg == "--layout" {
+            let name = args.next().unwrap_or_default();
+            match emulation::MemoryLayout::from_name(&name, emulation::Platform::Chip8) {
+                Some(_) => options.layout_name = name,
+                None => println!("Unknown memory layout: {}", name),
+            }
+        } else if arg == "--ram-size" {
+            let value = args.next().unwrap_or_default();
+            match value.parse() {
+                Ok(size) => options.ram_size = Some(size),
+                Err(_) => println!("Invalid ram size: {}", value),
+            }
+        } else if arg == "--config" {
+            config_path = args.next();
+        } else if arg == "--watch" {
+            options.watch = true;
+        } else {
+            rom_path = arg;
+        }
+    }
+
+    let config_path = match config_path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(PathBuf::from(DEFAULT_SETTINGS_PATH)).filter(|path| path.exists()),
+    };
+    if let Some(path) = config_path {
+        match emulation::Settings::load(&path) {
+            Ok(settings) => options.settings = settings,
+            Err(error) => {
+                println!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Refuse missing and oversized ROMs before opening a window
+    let rom_path = PathBuf::from(rom_path);
+    let mut browser = None;
+    let mut session = None;
+    if rom_path.is_dir() {
+        match emulation::RomBrowser::scan(&rom_path) {
+            Ok(scanned) => browser = Some(scanned),
+            Err(error) => {
+                println!("ROM directory could not be read: {}", error);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        match load_rom(&rom_path, &options) {
+            Ok(loaded) => session = Some(loaded),
+            Err(error) => {
+                println!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let mut display = emulation::Display::new(
         DEFAULT_CONFIG.width,
         DEFAULT_CONFIG.height,
         DEFAULT_CONFIG.scale,
         "Chip8 Emulator",
     );
+    display.scaling = options.settings.scaling;
+    if options.settings.fullscreen {
+        display.set_fullscreen(true);
+    }
+    // Themes are cycled with F9, ROMs with known colours use them until then
+    let palettes = options.settings.all_palettes();
+    let mut palette_index = options
+        .settings
+        .theme
+        .as_ref()
+        .and_then(|theme| palettes.iter().position(|p| &p.name == theme))
+        .unwrap_or(0);
+    display.set_colours(palettes[palette_index].colours);
+    if let Some(colours) = session.as_ref().and_then(|s| s.colours) {
+        display.set_colours(colours);
+    }
 
-    let disk = emulation::Disk::new("roms/Chip8_Logo.ch8");
-    disk.print_disk();
-
-    let mut cpu = emulation::Cpu::new();
-    cpu.load_disk_to_ram(&disk);
+    // Update events drive the scheduler at the timer frequency
+    display.window.set_ups(emulation::FRAME_RATE as u64);
 
     while let Some(e) = display.window.next() {
-        // Handle input
+        // Handle input, backspace leaves the running ROM for the browser,
+        // F12 and F10 take PNG and PBM screenshots, F8 starts and stops a GIF recording,
+        // F9 cycles the themes, F11 switches between window and fullscreen
         if let Some(Button::Keyboard(key)) = e.press_args() {
-            handle_input(&mut cpu, key);
+            if key == Key::F11 {
+                display.toggle_fullscreen();
+            } else if key == Key::F9 {
+                palette_index = (palette_index + 1) % palettes.len();
+                println!("Theme: {}", palettes[palette_index].name);
+                display.set_colours(palettes[palette_index].colours);
+            } else if let Some(running) = &mut session {
+                if key == Key::F12 || key == Key::F10 {
+                    take_screenshot(running, key == Key::F12, &display, &options);
+                } else if key == Key::F8 {
+                    match running.recording {
+                        Some(_) => stop_recording(running),
+                        None => start_recording(running, &display, &options),
+                    }
+                } else if key == Key::Backspace {
+                    let dir = match &browser {
+                        Some(browser) => browser.dir.clone(),
+                        None => rom_directory(&running.path),
+                    };
+                    match emulation::RomBrowser::scan(&dir) {
+                        Ok(mut scanned) => {
+                            scanned.select_path(&running.path);
+                            stop_recording(running);
+                            browser = Some(scanned);
+                            session = None;
+                            display.set_colours(palettes[palette_index].colours);
+                        }
+                        Err(error) => println!("ROM directory could not be read: {}", error),
+                    }
+                } else {
+                    handle_input(&mut running.cpu, key, true);
+                }
+            } else if let Some(browser) = &mut browser {
+                match key {
+                    Key::Up => browser.select_previous(),
+                    Key::Down => browser.select_next(),
+                    Key::Return => {
+                        if let Some(entry) = browser.selected() {
+                            match load_rom(&entry.path, &options) {
+                                Ok(loaded) => {
+                                    browser.message = None;
+                                    if let Some(colours) = loaded.colours {
+                                        display.set_colours(colours);
+                                    }
+                                    session = Some(loaded);
+                                }
+                                Err(error) => browser.message = Some(error),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.release_args() {
+            if let Some(running) = &mut session {
+                handle_input(&mut running.cpu, key, false);
+            }
         }
 
-        // Handle cpu
-        cpu.next();
+        // Handle cpu, halt on the first error
+        if let Some(args) = e.update_args() {
+            if let Some(running) = &mut session {
+                let elapsed = Duration::from_secs_f64(args.dt);
+                let changed = match &mut running.watcher {
+                    Some(watcher) => watcher.poll(elapsed),
+                    None => false,
+                };
+                if changed {
+                    match reload_rom(running, &options) {
+                        Ok(()) => println!("Reloaded {}", running.path.display()),
+                        Err(error) => println!("Reload failed, keeping the old ROM: {}", error),
+                    }
+                }
+                if running.halted.is_none() {
+                    match running.scheduler.advance(&mut running.cpu, elapsed) {
+                        Ok(0) => {}
+                        Ok(frames) => {
+                            record_frames(running, frames);
+                            running.blender.push(&mut running.cpu.framebuffer);
+                        }
+                        Err(error) => {
+                            println!("CPU halted: {}", error);
+                            running.halted = Some(error);
+                            running.blender.push(&mut running.cpu.framebuffer);
+                        }
+                    }
+                }
+            }
+        }
 
         // Handle display
-        display.draw(&cpu, &e);
+        if let Some(running) = &mut session {
+            display.draw(&mut running.blender, &e);
+        } else if let Some(browser) = &browser {
+            display.draw_browser(browser, &e);
+        }
+    }
+
+    // Don't lose a recording running when the window is closed
+    if let Some(running) = &mut session {
+        stop_recording(running);
+    }
+}
+
+// Save the framebuffer as a PNG in the display colours or as a PBM,
+// numbered after the ROM in the working directory
+fn take_screenshot(session: &Session, png: bool, display: &emulation::Display, options: &Options) {
+    let name = session
+        .path
+        .file_stem()
+        .map_or("screenshot".into(), |stem| stem.to_string_lossy());
+    let extension = if png { "png" } else { "pbm" };
+    let path = screenshot::next_free_path(Path::new("."), &name, extension);
+    let framebuffer = &session.cpu.framebuffer;
+    let result = if png {
+        let scale = options.settings.screenshot_scale;
+        screenshot::save_png(&path, framebuffer, &display.palette(), scale)
+    } else {
+        screenshot::save_pbm(&path, framebuffer)
+    };
+    match result {
+        Ok(()) => println!("Screenshot saved to {}", path.display()),
+        Err(error) => println!("{}", error),
+    }
+}
+
+// Record the framebuffer into a new GIF numbered after the ROM in the working directory
+fn start_recording(session: &mut Session, display: &emulation::Display, options: &Options) {
+    let name = session
+        .path
+        .file_stem()
+        .map_or("recording".into(), |stem| stem.to_string_lossy());
+    let path = screenshot::next_free_path(Path::new("."), &name, "gif");
+    let file = match File::create(&path) {
+        Ok(file) => BufWriter::new(file),
+        Err(error) => {
+            println!("Recording could not be started: {}", error);
+            return;
+        }
+    };
+    let scale = options.settings.recording_scale;
+    let palette = display.palette();
+    match emulation::GifRecorder::for_framebuffer(file, &session.cpu.framebuffer, scale, &palette) {
+        Ok(recorder) => {
+            println!("Recording to {}", path.display());
+            session.recording = Some((path, recorder));
+        }
+        Err(error) => println!("{}", error),
+    }
+}
+
+// Add the frames run since the last update, they all show the current framebuffer
+fn record_frames(session: &mut Session, frames: u32) {
+    if let Some((_, recorder)) = &mut session.recording {
+        for _ in 0..frames {
+            if let Err(error) = recorder.push(&session.cpu.framebuffer) {
+                println!("{}", error);
+                session.recording = None;
+                return;
+            }
+        }
+    }
+}
+
+fn stop_recording(session: &mut Session) {
+    if let Some((path, recorder)) = session.recording.take() {
+        let result = recorder
+            .finish()
+            .and_then(|mut file| file.flush().map_err(emulation::RecordError::from));
+        match result {
+            Ok(()) => println!("Recording saved to {}", path.display()),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+// Directory a ROM file lives in, for the browser
+fn rom_directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// Load a ROM into a fresh cpu, settings come from the command line, an Octo
+// cartridge, the ROM database and the file extension in that order
+fn load_rom(path: &Path, options: &Options) -> Result<Session, String> {
+    // The platform may still be unknown, the cpu checks the size again
+    let max_platform = options.platform.unwrap_or(emulation::Platform::XoChip);
+    let (disk, cartridge) = load_disk(path, memory_layout(options, max_platform))?;
+    disk.print_disk();
+
+    // Command line settings win over the cartridge and the ROM database
+    let info = disk.info;
+    if let Some(info) = info {
+        println!("{} by {}", info.title, info.author);
+        for (key, action) in info.keys {
+            let c = emulation::keyboard_char(*key).to_ascii_uppercase();
+            println!("  {} (key {:X}): {}", c, key, action);
+        }
+    }
+    if let Some(description) = &disk.description {
+        println!("{}", description);
+    }
+    let extension_platform = path
+        .extension()
+        .and_then(|extension| emulation::Platform::from_extension(&extension.to_string_lossy()));
+    let platform = options
+        .platform
+        .or(cartridge.as_ref().map(|cartridge| cartridge.platform))
+        .or(info.map(|info| info.platform))
+        .or(extension_platform)
+        .unwrap_or(emulation::Platform::Chip8);
+    let quirks = options
+        .quirks
+        .or(cartridge.as_ref().map(|cartridge| cartridge.quirks))
+        .or(info.map(|info| info.quirks))
+        .unwrap_or_else(|| platform.default_quirks());
+    let instructions_per_frame = options
+        .instructions_per_frame
+        .or(cartridge
+            .as_ref()
+            .map(|cartridge| cartridge.instructions_per_frame))
+        .or(info.map(|info| info.instructions_per_frame))
+        .unwrap_or(DEFAULT_CONFIG.instructions_per_frame);
+
+    let layout = memory_layout(options, platform);
+    let mut cpu = emulation::Cpu::with_layout(platform, quirks, layout)
+        .map_err(|error| format!("Invalid memory layout: {}", error))?;
+    if let Some(seed) = options.seed {
+        cpu.seed_rng(seed);
+    }
+    cpu.load_disk_to_ram(&disk).map_err(|e| e.to_string())?;
+    println!("Loaded {} bytes to RAM", disk.size);
+
+    // A theme picked in the settings wins over the colours of the cartridge and the ROM database
+    let colours = match options.settings.theme {
+        Some(_) => None,
+        None => cartridge
+            .and_then(|cartridge| cartridge.colours)
+            .or(info.and_then(|info| info.colours)),
+    };
+
+    let mut blender = emulation::FrameBlender::new(options.settings.anti_flicker);
+    blender.push(&mut cpu.framebuffer);
+
+    Ok(Session {
+        path: path.to_path_buf(),
+        cpu,
+        scheduler: emulation::Scheduler::new(instructions_per_frame),
+        blender,
+        halted: None,
+        colours,
+        recording: None,
+        watcher: options.watch.then(|| emulation::FileWatcher::new(path)),
+    })
+}
+
+// Memory layout picked on the command line, for a platform
+fn memory_layout(options: &Options, platform: emulation::Platform) -> emulation::MemoryLayout {
+    let mut layout = emulation::MemoryLayout::from_name(&options.layout_name, platform)
+        .unwrap_or_else(|| emulation::MemoryLayout::for_platform(platform));
+    if let Some(ram_size) = options.ram_size {
+        layout.ram_size = ram_size;
+    }
+    layout
+}
+
+// Read a ROM file, Octo cartridges are compiled and bring their settings along
+fn load_disk(
+    path: &Path,
+    layout: emulation::MemoryLayout,
+) -> Result<(emulation::Disk, Option<emulation::Cartridge>), String> {
+    let file_path = path.to_string_lossy();
+    let is_cartridge = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if is_cartridge {
+        let cartridge = emulation::Cartridge::load(&file_path).map_err(|e| e.to_string())?;
+        // Compiled programs get the size check of ROM files
+        let max = layout.max_program_size();
+        if cartridge.rom.len() > max {
+            let size = cartridge.rom.len();
+            return Err(emulation::DiskError::TooLarge { size, max }.to_string());
+        }
+        let disk = emulation::Disk::from_rom(cartridge.rom.clone());
+        return Ok((disk, Some(cartridge)));
+    }
+    let disk = emulation::Disk::load_with_layout(&file_path, layout).map_err(|e| e.to_string())?;
+    Ok((disk, None))
+}
+
+// Load the changed ROM file into the reset cpu of a session.
+// The settings the session started with are kept, even if the ROM database knows the new ROM.
+fn reload_rom(session: &mut Session, options: &Options) -> Result<(), String> {
+    // The size is checked before the reset so a failed reload leaves the old ROM running
+    let (disk, _) = load_disk(&session.path, session.cpu.layout)?;
+
+    session.cpu.reset();
+    if let Some(seed) = options.seed {
+        session.cpu.seed_rng(seed);
     }
+    session
+        .cpu
+        .load_disk_to_ram(&disk)
+        .map_err(|e| e.to_string())?;
+    session.scheduler = emulation::Scheduler::new(session.scheduler.instructions_per_frame);
+    session.halted = None;
+    session.blender = emulation::FrameBlender::new(options.settings.anti_flicker);
+    session.blender.push(&mut session.cpu.framebuffer);
+    Ok(())
 }